@@ -0,0 +1,66 @@
+use ggez::{
+    glam,
+    graphics::{Canvas, DrawParam},
+};
+
+use crate::{
+    animation::{AnimationTrait, timeline::Timeline},
+    window::WindowBackend,
+};
+
+/// Horizontal pointer speed in pixels per second needed to swing one frame further from the
+/// resting pose.
+const SWING_SPEED: f32 = 300.0;
+
+/// Animation shown while the user holds the companion with the mouse.
+///
+/// Frames are ordered from swinging fully to the left to swinging fully to the right, with the
+/// middle frame being the resting pose. The companion lags behind the pointer, so dragging to the
+/// right swings it to the left and vice versa.
+pub struct DragAnimation {
+    /// Frames from swinging left to swinging right.
+    pub timeline: Timeline,
+
+    /// Current horizontal speed of the pointer in pixels per second.
+    pub speed: f32,
+}
+
+impl DragAnimation {
+    /// Returns the index of the frame matching the current `speed`.
+    fn frame_index(&self) -> usize {
+        let frames = self.timeline.frames().len();
+        let center = (frames / 2) as f32;
+        let offset = (-self.speed / SWING_SPEED).round();
+        (center + offset).clamp(0.0, (frames - 1) as f32) as usize
+    }
+}
+
+impl AnimationTrait for DragAnimation {
+    /// Start the animation.
+    ///
+    /// Resets the swing to the resting pose.
+    fn start(&mut self) {
+        self.speed = 0.0;
+    }
+
+    /// Update the animation state.
+    ///
+    /// For `DragAnimation`, this is a no-op since the frame only depends on `speed`.
+    fn update(&mut self, _window: &mut dyn WindowBackend) {}
+
+    /// Draw the frame matching the current pointer speed at `(0, 0)` coordinates of the window.
+    ///
+    /// # Arguments
+    /// * `canvas` - The canvas to draw the frame onto.
+    fn draw(&self, canvas: &mut Canvas) {
+        self.timeline.frames()[self.frame_index()]
+            .draw(canvas, DrawParam::default().dest(glam::vec2(0.0, 0.0)));
+    }
+
+    /// Check whether the animation has finished.
+    ///
+    /// For `DragAnimation`, this always returns `false` because it lasts as long as the drag.
+    fn is_finished(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,208 @@
+use std::f32::consts::PI;
+
+use serde::Deserialize;
+
+/// Curve mapping the linear progress of a movement to the progress actually shown.
+///
+/// Selected per behavior under `[easing]` in `companion.toml`:
+///
+/// ```toml
+/// [easing]
+/// walk_left = "ease_in_out_sine"
+/// walk_right = "ease_in_out_sine"
+/// ```
+///
+/// See <https://easings.net> for plots of the curves. `elastic` curves briefly move past the start
+/// or the end, but always end on the target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    /// Constant speed.
+    #[default]
+    Linear,
+    /// Accelerates from zero speed.
+    EaseInQuad,
+    /// Decelerates to zero speed.
+    EaseOutQuad,
+    /// Accelerates until halfway, then decelerates.
+    EaseInOutQuad,
+    /// Like `EaseInQuad`, but more pronounced.
+    EaseInCubic,
+    /// Like `EaseOutQuad`, but more pronounced.
+    EaseOutCubic,
+    /// Like `EaseInOutQuad`, but more pronounced.
+    EaseInOutCubic,
+    /// Gently accelerates from zero speed.
+    EaseInSine,
+    /// Gently decelerates to zero speed.
+    EaseOutSine,
+    /// Gently accelerates until halfway, then decelerates.
+    EaseInOutSine,
+    /// Bounces off the start before leaving it.
+    EaseInBounce,
+    /// Bounces on the end before settling.
+    EaseOutBounce,
+    /// Bounces off the start and on the end.
+    EaseInOutBounce,
+    /// Winds up like a spring before leaving the start.
+    EaseInElastic,
+    /// Overshoots the end like a spring before settling.
+    EaseOutElastic,
+    /// Winds up at the start and overshoots the end.
+    EaseInOutElastic,
+}
+
+impl Easing {
+    /// Applies the curve to the linear progress `t`.
+    ///
+    /// # Arguments
+    /// * `t` - Linear progress, clamped to `0.0..=1.0`.
+    ///
+    /// # Returns
+    /// The eased progress, `0.0` at the start and `1.0` at the end.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInQuad => t * t,
+            Easing::EaseOutQuad => 1.0 - (1.0 - t).powi(2),
+            Easing::EaseInOutQuad => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::EaseInCubic => t.powi(3),
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOutCubic => {
+                if t < 0.5 {
+                    4.0 * t.powi(3)
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::EaseInSine => 1.0 - (t * PI / 2.0).cos(),
+            Easing::EaseOutSine => (t * PI / 2.0).sin(),
+            Easing::EaseInOutSine => -((PI * t).cos() - 1.0) / 2.0,
+            Easing::EaseInBounce => 1.0 - bounce_out(1.0 - t),
+            Easing::EaseOutBounce => bounce_out(t),
+            Easing::EaseInOutBounce => {
+                if t < 0.5 {
+                    (1.0 - bounce_out(1.0 - 2.0 * t)) / 2.0
+                } else {
+                    (1.0 + bounce_out(2.0 * t - 1.0)) / 2.0
+                }
+            }
+            Easing::EaseInElastic => 1.0 - elastic_out(1.0 - t),
+            Easing::EaseOutElastic => elastic_out(t),
+            Easing::EaseInOutElastic => {
+                if t < 0.5 {
+                    (1.0 - elastic_out(1.0 - 2.0 * t)) / 2.0
+                } else {
+                    (1.0 + elastic_out(2.0 * t - 1.0)) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// Decelerates into the end with three bounces of decreasing height.
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+/// Overshoots the end and settles on it like a spring.
+fn elastic_out(t: f32) -> f32 {
+    if t <= 0.0 || t >= 1.0 {
+        return t;
+    }
+    2f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Easing; 16] = [
+        Easing::Linear,
+        Easing::EaseInQuad,
+        Easing::EaseOutQuad,
+        Easing::EaseInOutQuad,
+        Easing::EaseInCubic,
+        Easing::EaseOutCubic,
+        Easing::EaseInOutCubic,
+        Easing::EaseInSine,
+        Easing::EaseOutSine,
+        Easing::EaseInOutSine,
+        Easing::EaseInBounce,
+        Easing::EaseOutBounce,
+        Easing::EaseInOutBounce,
+        Easing::EaseInElastic,
+        Easing::EaseOutElastic,
+        Easing::EaseInOutElastic,
+    ];
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn every_curve_starts_at_zero_and_ends_at_one() {
+        for easing in ALL {
+            assert_close(easing.apply(0.0), 0.0);
+            assert_close(easing.apply(1.0), 1.0);
+        }
+    }
+
+    #[test]
+    fn progress_is_clamped() {
+        for easing in ALL {
+            assert_close(easing.apply(-1.0), 0.0);
+            assert_close(easing.apply(2.0), 1.0);
+        }
+    }
+
+    #[test]
+    fn in_out_curves_are_halfway_at_half_time() {
+        assert_close(Easing::EaseInOutQuad.apply(0.5), 0.5);
+        assert_close(Easing::EaseInOutCubic.apply(0.5), 0.5);
+        assert_close(Easing::EaseInOutSine.apply(0.5), 0.5);
+    }
+
+    #[test]
+    fn ease_in_quad_squares_progress() {
+        assert_close(Easing::EaseInQuad.apply(0.5), 0.25);
+    }
+
+    #[test]
+    fn bounce_first_touches_the_end_at_one_over_2_75() {
+        assert_close(Easing::EaseOutBounce.apply(1.0 / 2.75), 1.0);
+        assert!(Easing::EaseOutBounce.apply(1.5 / 2.75) < 1.0);
+    }
+
+    #[test]
+    fn elastic_overshoots_the_end() {
+        let max = (1..100)
+            .map(|i| Easing::EaseOutElastic.apply(i as f32 / 100.0))
+            .fold(f32::MIN, f32::max);
+        assert!(max > 1.0, "never overshot, max {max}");
+    }
+}
@@ -1,50 +1,156 @@
 use ggez::{
-    Context, glam,
-    graphics::{Canvas, DrawParam, Image},
+    glam,
+    graphics::{Canvas, DrawParam},
 };
+use rand::Rng;
+use std::time::{Duration, Instant};
 
-use crate::animation::AnimationTrait;
+use crate::{
+    animation::{AnimationTrait, timeline::Timeline},
+    behavior::SharedRng,
+    clock::SharedClock,
+    window::WindowBackend,
+};
+
+/// Short animation played now and then while idling, such as blinking.
+#[derive(Debug, Clone)]
+pub struct Fidget {
+    /// Frames of the fidget, played through once each time.
+    pub timeline: Timeline,
+
+    /// Average time between two fidgets.
+    pub every: Duration,
+
+    /// Maximum random deviation from `every`, in both directions.
+    pub jitter: Duration,
+}
+
+impl Fidget {
+    /// Picks a random delay until the next occurrence of this fidget.
+    fn next_delay(&self, rng: &mut impl Rng) -> Duration {
+        let every = self.every.as_secs_f32();
+        let jitter = self.jitter.as_secs_f32().min(every);
+        let delay = if jitter > 0.0 {
+            rng.random_range(every - jitter..=every + jitter)
+        } else {
+            every
+        };
+        Duration::from_secs_f32(delay.max(0.1))
+    }
+}
 
 /// Animation that represents an idle state for a character.
 ///
-/// Currently, this animation simply displays the first sprite frame and
-/// does not advance or animate over time.
-///
-// TODO: Implement cycling over idle sprites
+/// Cycles through the idle frames according to their timeline and occasionally interrupts them
+/// with one of the companion's [`Fidget`]s.
 pub struct IdleAnimation {
-    /// All frames available for this animation.
-    ///
-    /// For `IdleAnimation`, only the first frame is used.
-    pub sprite_frames: Vec<Image>,
+    /// Frames of the idle animation.
+    pub timeline: Timeline,
+
+    /// Fidgets interrupting the idle animation.
+    pub fidgets: Vec<Fidget>,
+
+    /// Time when the animation started.
+    pub start_time: Instant,
+
+    /// When each fidget in `fidgets` is due next.
+    pub next_fidget: Vec<Instant>,
+
+    /// Index and start time of the fidget currently playing, if any.
+    pub active_fidget: Option<(usize, Instant)>,
+
+    /// Source of randomness for the delays between fidgets.
+    pub rng: SharedRng,
+
+    /// Source of the current time.
+    pub clock: SharedClock,
+}
+
+impl IdleAnimation {
+    /// Creates an idle animation with the given fidgets.
+    pub fn new(
+        timeline: Timeline,
+        fidgets: Vec<Fidget>,
+        rng: SharedRng,
+        clock: SharedClock,
+    ) -> Self {
+        let mut animation = Self {
+            timeline,
+            fidgets,
+            start_time: clock.now(),
+            next_fidget: Vec::new(),
+            active_fidget: None,
+            rng,
+            clock,
+        };
+        animation.start();
+        animation
+    }
 }
 
 impl AnimationTrait for IdleAnimation {
     /// Start the animation.
     ///
-    /// For `IdleAnimation`, this does nothing because it has no active timeline.
-    fn start(&mut self) {}
+    /// Restarts the idle timeline and schedules every fidget anew.
+    fn start(&mut self) {
+        let now = self.clock.now();
+        self.start_time = now;
+        self.active_fidget = None;
+        let mut rng = self.rng.lock().unwrap();
+        self.next_fidget = self
+            .fidgets
+            .iter()
+            .map(|fidget| now + fidget.next_delay(&mut *rng))
+            .collect();
+    }
 
     /// Update the animation state.
     ///
-    /// For `IdleAnimation`, this is a no-op since the idle animation does not change over time.
-    fn update(&mut self, _ctx: &mut Context) {}
+    /// Ends the playing fidget once it played through, and starts the next one that is due.
+    ///
+    /// # Arguments
+    /// * `_window` - Window of the companion (unused, idling happens in place).
+    fn update(&mut self, _window: &mut dyn WindowBackend) {
+        let now = self.clock.now();
+
+        if let Some((index, started)) = self.active_fidget {
+            if now.saturating_duration_since(started)
+                < self.fidgets[index].timeline.cycle_duration()
+            {
+                return;
+            }
+            self.active_fidget = None;
+            self.next_fidget[index] =
+                now + self.fidgets[index].next_delay(&mut *self.rng.lock().unwrap());
+        }
+
+        if let Some(index) = (0..self.fidgets.len()).find(|&i| self.next_fidget[i] <= now) {
+            self.active_fidget = Some((index, now));
+        }
+    }
 
     /// Draw the animation to the provided canvas.
     ///
-    /// Always draws the first frame at `(0, 0)` coordinates of the window.
+    /// Draws the current frame of the playing fidget, or of the idle timeline, at `(0, 0)`
+    /// coordinates of the window.
     ///
     /// # Arguments
     /// * `canvas` - The canvas to draw the frame onto.
     fn draw(&self, canvas: &mut Canvas) {
-        canvas.draw(
-            &self.sprite_frames[0],
-            DrawParam::default().dest(glam::vec2(0.0, 0.0)),
-        );
+        let frame = match self.active_fidget {
+            Some((index, started)) => self.fidgets[index]
+                .timeline
+                .frame(self.clock.elapsed(started)),
+            None => self.timeline.frame(self.clock.elapsed(self.start_time)),
+        };
+
+        frame.draw(canvas, DrawParam::default().dest(glam::vec2(0.0, 0.0)));
     }
 
     /// Check whether the animation has finished.
     ///
-    /// For `IdleAnimation`, this always returns `true` because there is no ongoing animation.
+    /// For `IdleAnimation`, this always returns `true` because idling never blocks the next
+    /// behavior.
     fn is_finished(&self) -> bool {
         true
     }
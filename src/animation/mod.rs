@@ -1,19 +1,72 @@
-use ggez::{Context, graphics::Canvas};
-use std::collections::hash_map::HashMap;
+use ggez::{
+    Context,
+    graphics::{Canvas, Color, DrawParam, Image},
+};
+use std::{
+    collections::hash_map::HashMap,
+    time::{Duration, Instant},
+};
 
+use crate::{
+    animation::{pose::PoseAnimation, timeline::Timeline},
+    clock::SharedClock,
+    window::WindowBackend,
+};
+
+pub mod drag;
+pub mod easing;
 pub mod idle;
 pub mod movement;
+pub mod physics;
+pub mod pose;
+pub mod sequence;
+pub mod timeline;
+
+/// Name of the animation shown while no other animation is active.
+const IDLE: &str = "idle";
 
 /// Manages multiple companion animations and tracks the currently active one.
 ///
 /// Provides functions to start, update, and draw animations by name. If no animation
 /// is active, it falls back to the `"idle"` animation if present.
+///
+/// Switching to another animation plays the transition frames declared for the pair, if any.
+/// Otherwise the previous animation fades out over `crossfade`.
 pub struct CompanionAnimations {
     /// Map of animation names to their boxed implementations of [`AnimationTrait`].
     pub animations: HashMap<String, Box<dyn AnimationTrait>>,
 
     /// Name of the currently active animation, if any.
     pub active: Option<String>,
+
+    /// Frames played when switching between two animations, keyed by `(from, to)`.
+    pub transitions: HashMap<(String, String), Timeline>,
+
+    /// How long the previous animation fades out after a switch without transition frames.
+    pub crossfade: Duration,
+
+    /// Transition frames currently playing, the active animation starts once they have
+    /// finished.
+    transition: Option<PoseAnimation>,
+
+    /// Previous animation currently fading out.
+    fade: Option<Fade>,
+
+    /// Offscreen image the fading animation is drawn to, created on the first fade and only
+    /// recreated when the window's size changes.
+    offscreen: Option<Image>,
+
+    /// Source of the current time.
+    clock: SharedClock,
+}
+
+/// Previous animation fading out on top of the current one.
+struct Fade {
+    /// Name of the previous animation.
+    from: String,
+
+    /// Time when the fade started.
+    start_time: Instant,
 }
 
 /// Trait that all companion animations must implement.
@@ -27,8 +80,8 @@ pub trait AnimationTrait {
     /// Updates the animation state. Typically called once per frame.
     ///
     /// # Arguments
-    /// * `ctx` - ggez context required for certain animation operations.
-    fn update(&mut self, ctx: &mut Context);
+    /// * `window` - Window of the companion, for animations that move it.
+    fn update(&mut self, window: &mut dyn WindowBackend);
 
     /// Draws the animation to the provided canvas.
     ///
@@ -42,10 +95,25 @@ pub trait AnimationTrait {
 
 impl CompanionAnimations {
     /// Creates a new empty `CompanionAnimations` manager with no active animation.
-    pub fn new() -> Self {
+    ///
+    /// # Arguments
+    /// * `transitions` - Frames played between two animations, keyed by `(from, to)`.
+    /// * `crossfade` - How long the previous animation fades out otherwise, zero to disable.
+    /// * `clock` - Source of the current time for transitions and fades.
+    pub fn new(
+        transitions: HashMap<(String, String), Timeline>,
+        crossfade: Duration,
+        clock: SharedClock,
+    ) -> Self {
         CompanionAnimations {
             animations: HashMap::new(),
             active: None,
+            transitions,
+            crossfade,
+            transition: None,
+            fade: None,
+            offscreen: None,
+            clock,
         }
     }
 
@@ -60,55 +128,301 @@ impl CompanionAnimations {
 
     /// Starts an animation by name and sets it as active.
     ///
+    /// If transition frames are declared from the animation shown so far, they play first and
+    /// the animation starts once they have finished.
+    ///
     /// # Arguments
     /// * `name` - Name of the animation to start.
-    /// * `_ctx` - ggez context (currently unused in this method).
-    pub fn start(&mut self, name: &str, _ctx: &mut Context) {
-        if let Some(anim) = self.animations.get_mut(name) {
-            anim.start();
-            self.active = Some(name.to_string());
+    pub fn start(&mut self, name: &str) {
+        if self.animations.contains_key(name) {
+            self.switch(Some(name.to_string()));
+        }
+    }
+
+    /// Returns `true` while an animation or transition is playing, and `false` while idling.
+    pub fn is_busy(&self) -> bool {
+        self.active.is_some() || self.transition.is_some()
+    }
+
+    /// Returns the name of the animation currently shown, `None` during transitions.
+    fn shown(&self) -> Option<&str> {
+        if self.transition.is_some() {
+            return None;
+        }
+        self.active
+            .as_deref()
+            .or_else(|| self.animations.contains_key(IDLE).then_some(IDLE))
+    }
+
+    /// Switches from the animation currently shown to `to`, `None` being the idle fallback.
+    fn switch(&mut self, to: Option<String>) {
+        let from = self.shown().map(str::to_string);
+        let to_name = to.clone().unwrap_or_else(|| IDLE.to_string());
+        self.active = to;
+
+        // restarting the animation shown, e.g. idling after idling, keeps an ongoing fade
+        if from.as_deref() == Some(to_name.as_str()) {
+            self.start_active();
+            return;
+        }
+
+        self.transition = None;
+        self.fade = None;
+        if let Some(from) = from {
+            if let Some(timeline) = self.transitions.get(&(from.clone(), to_name)) {
+                self.transition = Some(PoseAnimation {
+                    timeline: timeline.clone(),
+                    duration: timeline.cycle_duration().as_secs_f32(),
+                    start_time: self.clock.now(),
+                    clock: self.clock.clone(),
+                });
+                return;
+            }
+
+            if !self.crossfade.is_zero() {
+                self.fade = Some(Fade {
+                    from,
+                    start_time: self.clock.now(),
+                });
+            }
+        }
+        self.start_active();
+    }
+
+    /// Starts the active animation, if any.
+    fn start_active(&mut self) {
+        if let Some(animation) = self
+            .active
+            .as_ref()
+            .and_then(|name| self.animations.get_mut(name))
+        {
+            animation.start();
         }
     }
 
     /// Updates the currently active animation.
     ///
-    /// If the active animation has finished, clears the `active` field.
+    /// If the active animation has finished, clears the `active` field. If no animation is
+    /// active, updates the `"idle"` animation instead, since that's the one being drawn.
+    /// Transition frames hold back the animation that follows them until they have finished.
     ///
     /// # Arguments
-    /// * `ctx` - ggez context for updating animations.
-    pub fn update(&mut self, ctx: &mut Context) {
+    /// * `window` - Window of the companion, for animations that move it.
+    pub fn update(&mut self, window: &mut dyn WindowBackend) {
+        if let Some(transition) = &mut self.transition {
+            transition.update(window);
+            if transition.is_finished() {
+                self.transition = None;
+                self.start_active();
+            }
+            return;
+        }
+
+        if self
+            .fade
+            .as_ref()
+            .is_some_and(|fade| self.clock.elapsed(fade.start_time) >= self.crossfade)
+        {
+            self.fade = None;
+        }
+
         if let Some(active_name) = &self.active {
             let finished = if let Some(anim) = self.animations.get_mut(active_name) {
-                anim.update(ctx);
+                anim.update(window);
                 anim.is_finished()
             } else {
                 false
             };
 
             if finished {
-                self.active = None;
+                self.switch(None);
             }
+        } else if let Some(idle_anim) = self.animations.get_mut(IDLE) {
+            idle_anim.update(window);
         }
     }
 
     /// Draws the active animation to the canvas.
     ///
-    /// If no animation is active, draws the `"idle"` animation if it exists.
+    /// If no animation is active, draws the `"idle"` animation if it exists. During a crossfade
+    /// the previous animation is drawn on top, more transparent with every frame.
     ///
     /// # Arguments
-    /// * `_ctx` - ggez context (currently unused in this method).
+    /// * `ctx` - ggez context, needed to draw the previous animation offscreen.
     /// * `canvas` - Canvas to draw the animation onto.
-    pub fn draw(&self, _ctx: &mut Context, canvas: &mut Canvas) {
-        if let Some(active_name) = &self.active {
-            if let Some(anim) = self.animations.get(active_name) {
-                anim.draw(canvas);
-                return;
+    pub fn draw(&mut self, ctx: &mut Context, canvas: &mut Canvas) {
+        if let Some(transition) = &self.transition {
+            transition.draw(canvas);
+            return;
+        }
+
+        if let Some(anim) = self.shown().and_then(|name| self.animations.get(name)) {
+            anim.draw(canvas);
+        }
+
+        let Some(fade) = &self.fade else {
+            return;
+        };
+        let Some(from) = self.animations.get(&fade.from) else {
+            return;
+        };
+        let alpha =
+            1.0 - self.clock.elapsed(fade.start_time).as_secs_f32() / self.crossfade.as_secs_f32();
+        if alpha <= 0.0 {
+            return;
+        }
+
+        // the previous animation is drawn as a whole, so overlapping parts don't shine through
+        let (width, height) = ctx.gfx.drawable_size();
+        let size = (width as u32, height as u32);
+        let target = match &self.offscreen {
+            Some(image) if (image.width(), image.height()) == size => image.clone(),
+            _ => {
+                let image =
+                    Image::new_canvas_image(ctx, ctx.gfx.surface_format(), size.0, size.1, 1);
+                self.offscreen = Some(image.clone());
+                image
             }
+        };
+        let mut offscreen = Canvas::from_image(ctx, target.clone(), Color::new(0.0, 0.0, 0.0, 0.0));
+        from.draw(&mut offscreen);
+        if offscreen.finish(ctx).is_ok() {
+            canvas.draw(
+                &target,
+                DrawParam::default().color(Color::new(1.0, 1.0, 1.0, alpha)),
+            );
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ggez::graphics::Rect;
 
-        // Fallback to idle animation
-        if let Some(idle_anim) = self.animations.get("idle") {
-            idle_anim.draw(canvas);
+    use super::*;
+    use crate::{
+        animation::timeline::Frame,
+        clock::{Clock, ManualClock},
+        companion::LoopMode,
+        window::VirtualWindow,
+    };
+
+    /// Timeline of two frames lasting 100ms each, played once.
+    fn timeline() -> Timeline {
+        let frame = Frame {
+            image: None,
+            src: Rect::one(),
+            duration: Duration::from_millis(100),
+        };
+        Timeline::new(vec![frame; 2], LoopMode::Once)
+    }
+
+    /// Animations `idle`, `walk` and `jump`, each finishing after `seconds` once started.
+    fn animations(
+        transitions: HashMap<(String, String), Timeline>,
+        crossfade: Duration,
+        clock: &Arc<ManualClock>,
+        seconds: f32,
+    ) -> CompanionAnimations {
+        let mut animations = CompanionAnimations::new(transitions, crossfade, clock.clone());
+        for name in ["idle", "walk", "jump"] {
+            let pose = PoseAnimation {
+                timeline: timeline(),
+                duration: seconds,
+                start_time: clock.now(),
+                clock: clock.clone(),
+            };
+            animations.push(Box::new(pose), name.to_string());
         }
+        animations
+    }
+
+    fn fading_from(animations: &CompanionAnimations) -> Option<&str> {
+        animations.fade.as_ref().map(|fade| fade.from.as_str())
+    }
+
+    #[test]
+    fn transition_plays_before_the_animation_starts() {
+        let clock = Arc::new(ManualClock::new());
+        let transitions = HashMap::from([(("idle".to_string(), "walk".to_string()), timeline())]);
+        let mut animations = animations(transitions, Duration::from_millis(150), &clock, 1.0);
+        let mut window = VirtualWindow::new((800.0, 600.0));
+
+        animations.start("walk");
+        assert!(animations.transition.is_some());
+        assert_eq!(animations.shown(), None);
+        assert_eq!(fading_from(&animations), None, "transitions don't fade");
+
+        clock.advance(Duration::from_millis(100));
+        animations.update(&mut window);
+        assert!(animations.transition.is_some());
+
+        // the walk only starts once the transition has finished
+        clock.advance(Duration::from_millis(100));
+        animations.update(&mut window);
+        assert!(animations.transition.is_none());
+        assert_eq!(animations.shown(), Some("walk"));
+
+        clock.advance(Duration::from_millis(900));
+        animations.update(&mut window);
+        assert_eq!(animations.active.as_deref(), Some("walk"));
+
+        clock.advance(Duration::from_millis(100));
+        animations.update(&mut window);
+        assert_eq!(animations.active, None);
+        assert!(!animations.is_busy());
+    }
+
+    #[test]
+    fn switching_during_a_fade_fades_from_the_latest_animation() {
+        let clock = Arc::new(ManualClock::new());
+        let mut animations = animations(HashMap::new(), Duration::from_millis(150), &clock, 10.0);
+        let mut window = VirtualWindow::new((800.0, 600.0));
+
+        animations.start("walk");
+        assert_eq!(fading_from(&animations), Some("idle"));
+
+        clock.advance(Duration::from_millis(100));
+        animations.update(&mut window);
+        animations.start("jump");
+        assert_eq!(fading_from(&animations), Some("walk"));
+        assert_eq!(animations.fade.as_ref().unwrap().start_time, clock.now());
+
+        // the first fade would be over by now, the second one isn't
+        clock.advance(Duration::from_millis(100));
+        animations.update(&mut window);
+        assert_eq!(fading_from(&animations), Some("walk"));
+
+        clock.advance(Duration::from_millis(50));
+        animations.update(&mut window);
+        assert_eq!(fading_from(&animations), None);
+        assert_eq!(animations.shown(), Some("jump"));
+    }
+
+    #[test]
+    fn switching_to_the_shown_animation_restarts_it() {
+        let clock = Arc::new(ManualClock::new());
+        let transitions = HashMap::from([(("walk".to_string(), "walk".to_string()), timeline())]);
+        let mut animations = animations(transitions, Duration::from_millis(150), &clock, 1.0);
+        let mut window = VirtualWindow::new((800.0, 600.0));
+
+        animations.start("walk");
+        clock.advance(Duration::from_millis(100));
+        animations.update(&mut window);
+
+        // neither the transition nor a new fade play, the ongoing fade goes on
+        animations.start("walk");
+        assert!(animations.transition.is_none());
+        assert_eq!(fading_from(&animations), Some("idle"));
+
+        // restarted, so it lasts a full second from here
+        clock.advance(Duration::from_millis(950));
+        animations.update(&mut window);
+        assert_eq!(animations.active.as_deref(), Some("walk"));
+        clock.advance(Duration::from_millis(50));
+        animations.update(&mut window);
+        assert_eq!(animations.active, None);
     }
 }
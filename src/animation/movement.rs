@@ -1,19 +1,17 @@
-use crate::animation::AnimationTrait;
+use crate::{
+    animation::{AnimationTrait, easing::Easing, timeline::Timeline},
+    clock::SharedClock,
+    window::WindowBackend,
+};
 use ggez::{
-    Context, glam,
-    graphics::{Canvas, DrawParam, Image},
-    winit::dpi::LogicalPosition,
+    glam,
+    graphics::{Canvas, DrawParam},
 };
 use std::time::Instant;
 
-/// Speed multiplier for walking animation frames.
-const WALKSPEED: f32 = 5.0;
-
 /// Direction of movement for the animation.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Direction {
-    /// Moving vertically (up/down)
-    Vertical,
     /// Moving left
     Left,
     /// Moving right
@@ -41,44 +39,49 @@ pub struct MoveAnimation {
     /// Whether the animation has finished.
     pub finished: bool,
 
-    /// Sprite frames for walking animation.
-    pub sprite_frames: Vec<Image>,
+    /// Frames of the walking animation.
+    pub timeline: Timeline,
 
     /// Current position `(x, y)` updated over time.
     pub current_pos: (f32, f32),
 
     /// Direction of movement.
     pub direction: Direction,
+
+    /// Curve applied to the progress of the movement.
+    pub easing: Easing,
+
+    /// Source of the current time.
+    pub clock: SharedClock,
 }
 
 impl AnimationTrait for MoveAnimation {
     /// Initializes the animation, recording the start time and resetting the finished flag.
     fn start(&mut self) {
-        self.start_time = Instant::now();
+        self.start_time = self.clock.now();
         self.finished = false;
         self.current_pos = self.start_pos;
     }
 
     /// Updates the animation state.
     ///
-    /// - Interpolates the position based on elapsed time and duration.
-    /// - Updates the window's logical position to match the current position.
+    /// - Interpolates the position based on elapsed time and duration, shaped by `easing`.
+    /// - Moves the window to the current position.
     /// - Marks the animation as finished if the end position is reached.
     ///
     /// # Arguments
-    /// * `ctx` - ggez context required to move the window.
-    fn update(&mut self, ctx: &mut Context) {
-        let elapsed = self.start_time.elapsed().as_secs_f32();
+    /// * `window` - Window of the companion to move.
+    fn update(&mut self, window: &mut dyn WindowBackend) {
+        let elapsed = self.clock.elapsed(self.start_time).as_secs_f32();
         let t = (elapsed / self.duration).min(1.0);
+        let progress = self.easing.apply(t);
 
         self.current_pos = (
-            self.start_pos.0 + (self.end.0 - self.start_pos.0) * t,
-            self.start_pos.1 + (self.end.1 - self.start_pos.1) * t,
+            self.start_pos.0 + (self.end.0 - self.start_pos.0) * progress,
+            self.start_pos.1 + (self.end.1 - self.start_pos.1) * progress,
         );
 
-        ctx.gfx
-            .window()
-            .set_outer_position(LogicalPosition::new(self.current_pos.0, self.current_pos.1));
+        window.set_position(self.current_pos);
 
         if t >= 1.0 {
             self.finished = true;
@@ -87,26 +90,24 @@ impl AnimationTrait for MoveAnimation {
 
     /// Draws the current frame of the animation to the canvas.
     ///
-    /// - Chooses a sprite frame based on elapsed time and `WALKSPEED`.
+    /// - Chooses a sprite frame from the timeline based on elapsed time.
     /// - Mirrors the sprite horizontally if moving to the right.
     ///
+    /// Keeps drawing after the animation has finished, so it can be faded out.
+    ///
     /// # Arguments
     /// * `canvas` - The canvas to draw the sprite onto.
     fn draw(&self, canvas: &mut Canvas) {
-        if !self.is_finished() {
-            let frame_index = ((self.start_time.elapsed().as_secs_f32() * WALKSPEED) as usize)
-                % self.sprite_frames.len();
-            let sprite = &self.sprite_frames[frame_index];
-
-            let mut param = DrawParam::default().dest(glam::vec2(0.0, 0.0));
-            if self.direction == Direction::Right {
-                param = param
-                    .scale(glam::vec2(-1.0, 1.0)) // mirror horizontally
-                    .offset(glam::vec2(1.0, 0.0)); // pivot around center
-            }
-
-            canvas.draw(sprite, param);
+        let sprite = self.timeline.frame(self.clock.elapsed(self.start_time));
+
+        let mut param = DrawParam::default().dest(glam::vec2(0.0, 0.0));
+        if self.direction == Direction::Right {
+            param = param
+                .scale(glam::vec2(-1.0, 1.0)) // mirror horizontally
+                .offset(glam::vec2(1.0, 0.0)); // pivot around center
         }
+
+        sprite.draw(canvas, param);
     }
 
     /// Returns true if the movement animation has finished.
@@ -114,3 +115,48 @@ impl AnimationTrait for MoveAnimation {
         self.finished
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::*;
+    use crate::{
+        clock::{Clock, ManualClock},
+        companion::LoopMode,
+        window::VirtualWindow,
+    };
+
+    #[test]
+    fn position_follows_the_clock() {
+        let clock = Arc::new(ManualClock::new());
+        let mut animation = MoveAnimation {
+            start_pos: (100.0, 50.0),
+            end: (300.0, 50.0),
+            duration: 2.0,
+            start_time: clock.now(),
+            finished: false,
+            timeline: Timeline::new(Vec::new(), LoopMode::Loop),
+            current_pos: (100.0, 50.0),
+            direction: Direction::Right,
+            easing: Easing::Linear,
+            clock: clock.clone(),
+        };
+        let mut window = VirtualWindow::new((1920.0, 1080.0));
+
+        animation.start();
+        animation.update(&mut window);
+        assert_eq!(window.position, (100.0, 50.0));
+        assert!(!animation.is_finished());
+
+        clock.advance(Duration::from_secs(1));
+        animation.update(&mut window);
+        assert_eq!(window.position, (200.0, 50.0));
+        assert!(!animation.is_finished());
+
+        clock.advance(Duration::from_secs(1));
+        animation.update(&mut window);
+        assert_eq!(window.position, (300.0, 50.0));
+        assert!(animation.is_finished());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{
+    animation::{AnimationTrait, timeline::Timeline},
+    clock::SharedClock,
+    physics::{Body, Bounds, PhysicsConfig},
+    window::WindowBackend,
+};
+use ggez::{
+    glam,
+    graphics::{Canvas, DrawParam},
+};
+use std::time::Instant;
+
+/// Animation that moves a character with the [`physics`](crate::physics) simulation.
+///
+/// Used for falling, jumping and dropping the companion after a drag. The animation finishes
+/// once the body comes to rest on the ground.
+#[derive(Debug)]
+pub struct PhysicsAnimation {
+    /// Simulated body of the character.
+    pub body: Body,
+
+    /// Physical parameters of the companion.
+    pub config: PhysicsConfig,
+
+    /// Area the character may move in.
+    pub bounds: Bounds,
+
+    /// Time when the animation started.
+    pub start_time: Instant,
+
+    /// Time of the previous simulation step.
+    pub last_update: Instant,
+
+    /// Whether the animation has finished.
+    pub finished: bool,
+
+    /// Frames shown while airborne.
+    pub timeline: Timeline,
+
+    /// Source of the current time.
+    pub clock: SharedClock,
+}
+
+impl PhysicsAnimation {
+    /// Creates an animation simulating `body` with the given parameters.
+    pub fn new(
+        body: Body,
+        config: PhysicsConfig,
+        bounds: Bounds,
+        timeline: Timeline,
+        clock: SharedClock,
+    ) -> Self {
+        let now = clock.now();
+        Self {
+            body,
+            config,
+            bounds,
+            start_time: now,
+            last_update: now,
+            finished: false,
+            timeline,
+            clock,
+        }
+    }
+}
+
+impl AnimationTrait for PhysicsAnimation {
+    /// Initializes the animation, recording the start time and resetting the finished flag.
+    fn start(&mut self) {
+        self.start_time = self.clock.now();
+        self.last_update = self.start_time;
+        self.finished = false;
+    }
+
+    /// Updates the animation state.
+    ///
+    /// - Steps the simulation by the time elapsed since the previous update.
+    /// - Moves the window to the position of the body.
+    /// - Marks the animation as finished once the body is resting on the ground.
+    ///
+    /// # Arguments
+    /// * `window` - Window of the companion to move.
+    fn update(&mut self, window: &mut dyn WindowBackend) {
+        let now = self.clock.now();
+        let dt = now
+            .saturating_duration_since(self.last_update)
+            .as_secs_f32();
+        self.last_update = now;
+
+        self.body.step(dt, &self.config, &self.bounds);
+
+        window.set_position(self.body.pos);
+
+        if self.body.is_resting() {
+            self.finished = true;
+        }
+    }
+
+    /// Draws the current frame of the animation to the canvas.
+    ///
+    /// - Chooses a sprite frame from the timeline based on elapsed time.
+    /// - Mirrors the sprite horizontally while moving to the right.
+    ///
+    /// Keeps drawing after the animation has finished, so it can be faded out.
+    ///
+    /// # Arguments
+    /// * `canvas` - The canvas to draw the sprite onto.
+    fn draw(&self, canvas: &mut Canvas) {
+        let sprite = self.timeline.frame(self.clock.elapsed(self.start_time));
+
+        let mut param = DrawParam::default().dest(glam::vec2(0.0, 0.0));
+        if self.body.vel.0 > 0.0 {
+            param = param
+                .scale(glam::vec2(-1.0, 1.0)) // mirror horizontally
+                .offset(glam::vec2(1.0, 0.0)); // pivot around center
+        }
+
+        sprite.draw(canvas, param);
+    }
+
+    /// Returns true once the character has landed and stopped.
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
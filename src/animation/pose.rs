@@ -0,0 +1,58 @@
+use ggez::{
+    glam,
+    graphics::{Canvas, DrawParam},
+};
+use std::time::Instant;
+
+use crate::{
+    animation::{AnimationTrait, timeline::Timeline},
+    clock::SharedClock,
+    window::WindowBackend,
+};
+
+/// Animation that cycles through frames in place for a fixed amount of time.
+///
+/// Used for short reactions such as being dizzy after a throw.
+#[derive(Debug)]
+pub struct PoseAnimation {
+    /// Frames to play.
+    pub timeline: Timeline,
+
+    /// How long the pose is held in seconds.
+    pub duration: f32,
+
+    /// Time when the animation started.
+    pub start_time: Instant,
+
+    /// Source of the current time.
+    pub clock: SharedClock,
+}
+
+impl AnimationTrait for PoseAnimation {
+    /// Initializes the animation, recording the start time.
+    fn start(&mut self) {
+        self.start_time = self.clock.now();
+    }
+
+    /// Update the animation state.
+    ///
+    /// For `PoseAnimation`, this is a no-op since the window doesn't move.
+    fn update(&mut self, _window: &mut dyn WindowBackend) {}
+
+    /// Draws the current frame of the animation to the canvas.
+    ///
+    /// Chooses a sprite frame from the timeline based on elapsed time.
+    ///
+    /// # Arguments
+    /// * `canvas` - The canvas to draw the frame onto.
+    fn draw(&self, canvas: &mut Canvas) {
+        self.timeline
+            .frame(self.clock.elapsed(self.start_time))
+            .draw(canvas, DrawParam::default().dest(glam::vec2(0.0, 0.0)));
+    }
+
+    /// Returns true once the pose has been held for `duration` seconds.
+    fn is_finished(&self) -> bool {
+        self.clock.elapsed(self.start_time).as_secs_f32() >= self.duration
+    }
+}
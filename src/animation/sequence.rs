@@ -0,0 +1,291 @@
+use ggez::graphics::Canvas;
+
+use crate::{animation::AnimationTrait, window::WindowBackend};
+
+/// Animation built from other animations, played one step after another.
+///
+/// Each step holds one or more animations that start together and run in parallel, the step
+/// ends once all of them have finished. This allows composing scripted actions such as
+/// "walk to the edge, then sit, then wave" from the primitive animations, or pairing a movement
+/// with the frames of another animation.
+///
+/// Only the last animation of the current step is drawn, so in a parallel step the movement
+/// should come first and the animation providing the frames last.
+#[derive(Default)]
+pub struct SequenceAnimation {
+    /// Steps in playback order.
+    steps: Vec<Vec<Box<dyn AnimationTrait>>>,
+
+    /// Index of the step currently playing, equal to the number of steps once finished.
+    current: usize,
+}
+
+impl SequenceAnimation {
+    /// Creates an empty sequence, which is finished right away.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a step playing `animation` after the previous steps.
+    pub fn then(mut self, animation: Box<dyn AnimationTrait>) -> Self {
+        self.steps.push(vec![animation]);
+        self
+    }
+
+    /// Adds `animation` to the last step, playing it in parallel with the animations there.
+    ///
+    /// Starts a new step if the sequence is empty.
+    pub fn with(mut self, animation: Box<dyn AnimationTrait>) -> Self {
+        match self.steps.last_mut() {
+            Some(step) => step.push(animation),
+            None => self.steps.push(vec![animation]),
+        }
+        self
+    }
+
+    /// Starts every animation of the current step, if there is one.
+    fn start_step(&mut self) {
+        if let Some(step) = self.steps.get_mut(self.current) {
+            for animation in step {
+                animation.start();
+            }
+        }
+    }
+}
+
+impl AnimationTrait for SequenceAnimation {
+    /// Starts the sequence from its first step.
+    fn start(&mut self) {
+        self.current = 0;
+        self.start_step();
+    }
+
+    /// Updates the animations of the current step and moves on to the next step once all of
+    /// them have finished. The next step is updated right away, so it has something to draw.
+    ///
+    /// # Arguments
+    /// * `window` - Window of the companion, passed on to the animations.
+    fn update(&mut self, window: &mut dyn WindowBackend) {
+        let Some(step) = self.steps.get_mut(self.current) else {
+            return;
+        };
+
+        for animation in step.iter_mut() {
+            if !animation.is_finished() {
+                animation.update(window);
+            }
+        }
+
+        if step.iter().all(|animation| animation.is_finished()) {
+            self.current += 1;
+            self.start_step();
+            self.update(window);
+        }
+    }
+
+    /// Draws the last animation of the current step.
+    ///
+    /// # Arguments
+    /// * `canvas` - The canvas to draw onto.
+    fn draw(&self, canvas: &mut Canvas) {
+        let index = self.current.min(self.steps.len().saturating_sub(1));
+        if let Some(animation) = self.steps.get(index).and_then(|step| step.last()) {
+            animation.draw(canvas);
+        }
+    }
+
+    /// Returns true once every step has finished.
+    fn is_finished(&self) -> bool {
+        self.current >= self.steps.len()
+    }
+}
+
+/// Function creating the animation of a [`LazyAnimation`].
+type Builder = Box<dyn FnMut(&dyn WindowBackend) -> Box<dyn AnimationTrait>>;
+
+/// Animation created only when it first updates.
+///
+/// Some animations depend on the state of the window when they begin, like a walk that starts
+/// wherever the previous step of a [`SequenceAnimation`] left the companion. The animation is
+/// built again every time this one is started.
+pub struct LazyAnimation {
+    /// Creates the animation from the current state.
+    build: Builder,
+
+    /// Animation created by `build`, `None` until the first update.
+    inner: Option<Box<dyn AnimationTrait>>,
+}
+
+impl LazyAnimation {
+    /// Creates an animation that is built by `build` when it first updates.
+    pub fn new(build: impl FnMut(&dyn WindowBackend) -> Box<dyn AnimationTrait> + 'static) -> Self {
+        Self {
+            build: Box::new(build),
+            inner: None,
+        }
+    }
+}
+
+impl AnimationTrait for LazyAnimation {
+    /// Discards the previously built animation, so the next update builds a new one.
+    fn start(&mut self) {
+        self.inner = None;
+    }
+
+    /// Builds and starts the animation if needed, then updates it.
+    ///
+    /// # Arguments
+    /// * `window` - Window of the companion, passed on to `build` and the animation.
+    fn update(&mut self, window: &mut dyn WindowBackend) {
+        let inner = self.inner.get_or_insert_with(|| {
+            let mut animation = (self.build)(window);
+            animation.start();
+            animation
+        });
+        inner.update(window);
+    }
+
+    /// Draws the built animation, or nothing before the first update.
+    fn draw(&self, canvas: &mut Canvas) {
+        if let Some(inner) = &self.inner {
+            inner.draw(canvas);
+        }
+    }
+
+    /// Returns true once the built animation has finished.
+    fn is_finished(&self) -> bool {
+        self.inner.as_ref().is_some_and(|inner| inner.is_finished())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, sync::Arc, time::Duration};
+
+    use super::*;
+    use crate::{
+        animation::{
+            easing::Easing,
+            movement::{Direction, MoveAnimation},
+            pose::PoseAnimation,
+            timeline::Timeline,
+        },
+        clock::{ManualClock, SharedClock},
+        companion::LoopMode,
+        window::VirtualWindow,
+    };
+
+    fn walk(clock: &SharedClock, from: (f32, f32), to_x: f32, seconds: f32) -> MoveAnimation {
+        MoveAnimation {
+            start_pos: from,
+            end: (to_x, from.1),
+            duration: seconds,
+            start_time: clock.now(),
+            finished: false,
+            timeline: Timeline::new(Vec::new(), LoopMode::Loop),
+            current_pos: from,
+            direction: Direction::Right,
+            easing: Easing::Linear,
+            clock: clock.clone(),
+        }
+    }
+
+    fn pose(clock: &SharedClock, seconds: f32) -> PoseAnimation {
+        PoseAnimation {
+            timeline: Timeline::new(Vec::new(), LoopMode::Loop),
+            duration: seconds,
+            start_time: clock.now(),
+            clock: clock.clone(),
+        }
+    }
+
+    #[test]
+    fn empty_sequence_is_finished() {
+        assert!(SequenceAnimation::new().is_finished());
+    }
+
+    #[test]
+    fn steps_play_one_after_another() {
+        let manual = Arc::new(ManualClock::new());
+        let clock: SharedClock = manual.clone();
+        let mut window = VirtualWindow::new((800.0, 600.0));
+        let mut sequence = SequenceAnimation::new()
+            .then(Box::new(walk(&clock, (0.0, 0.0), 100.0, 1.0)))
+            .then(Box::new(pose(&clock, 1.0)));
+
+        sequence.start();
+        sequence.update(&mut window);
+        assert_eq!(window.position, (0.0, 0.0));
+
+        // the pose only starts once the walk has arrived
+        manual.advance(Duration::from_secs(1));
+        sequence.update(&mut window);
+        assert_eq!(window.position, (100.0, 0.0));
+        assert!(!sequence.is_finished());
+
+        manual.advance(Duration::from_millis(500));
+        sequence.update(&mut window);
+        assert!(!sequence.is_finished());
+
+        manual.advance(Duration::from_millis(500));
+        sequence.update(&mut window);
+        assert!(sequence.is_finished());
+    }
+
+    #[test]
+    fn parallel_step_waits_for_all_animations() {
+        let manual = Arc::new(ManualClock::new());
+        let clock: SharedClock = manual.clone();
+        let mut window = VirtualWindow::new((800.0, 600.0));
+        let mut sequence = SequenceAnimation::new()
+            .then(Box::new(pose(&clock, 2.0)))
+            .with(Box::new(walk(&clock, (0.0, 0.0), 100.0, 1.0)));
+
+        sequence.start();
+        manual.advance(Duration::from_secs(1));
+        sequence.update(&mut window);
+        assert_eq!(window.position, (100.0, 0.0));
+        assert!(!sequence.is_finished());
+
+        manual.advance(Duration::from_secs(1));
+        sequence.update(&mut window);
+        assert!(sequence.is_finished());
+    }
+
+    #[test]
+    fn lazy_animation_is_built_where_the_window_is() {
+        let manual = Arc::new(ManualClock::new());
+        let clock: SharedClock = manual.clone();
+        let builds = Rc::new(Cell::new(0));
+        let mut lazy = LazyAnimation::new({
+            let builds = builds.clone();
+            move |window| {
+                builds.set(builds.get() + 1);
+                let start = window.position().unwrap();
+                Box::new(walk(&clock, start, start.0 + 100.0, 1.0))
+            }
+        });
+        let mut window = VirtualWindow::new((800.0, 600.0));
+        window.position = (50.0, 0.0);
+
+        lazy.start();
+        assert!(!lazy.is_finished());
+        assert_eq!(builds.get(), 0);
+
+        lazy.update(&mut window);
+        manual.advance(Duration::from_secs(1));
+        lazy.update(&mut window);
+        assert_eq!(window.position, (150.0, 0.0));
+        assert!(lazy.is_finished());
+
+        // starting again builds a new walk from the current position
+        window.position = (0.0, 0.0);
+        lazy.start();
+        assert!(!lazy.is_finished());
+        lazy.update(&mut window);
+        manual.advance(Duration::from_secs(1));
+        lazy.update(&mut window);
+        assert_eq!(window.position, (100.0, 0.0));
+        assert_eq!(builds.get(), 2);
+    }
+}
@@ -0,0 +1,158 @@
+use ggez::graphics::{Canvas, DrawParam, Image, Rect};
+use std::time::Duration;
+
+use crate::companion::LoopMode;
+
+/// A single frame of a [`Timeline`].
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Image shown during the frame, possibly a whole sprite sheet. `None` for companions
+    /// running without graphics, which only need to know how long frames last.
+    pub image: Option<Image>,
+
+    /// Region of `image` to show, in normalized `0.0..=1.0` coordinates.
+    pub src: Rect,
+
+    /// How long the frame is shown.
+    pub duration: Duration,
+}
+
+impl Frame {
+    /// Draws the frame's region of its image with `param`, if it has an image.
+    pub fn draw(&self, canvas: &mut Canvas, param: DrawParam) {
+        if let Some(image) = &self.image {
+            canvas.draw(image, param.src(self.src));
+        }
+    }
+}
+
+/// Sequence of frames with individual durations, played according to a [`LoopMode`].
+///
+/// Timelines are stateless: animations keep track of when they started and ask the timeline
+/// which frame to show after a given amount of time.
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    /// Frames in playback order.
+    frames: Vec<Frame>,
+
+    /// What happens after the last frame.
+    loop_mode: LoopMode,
+}
+
+impl Timeline {
+    /// Creates a timeline from `frames`.
+    ///
+    /// # Arguments
+    /// * `frames` - Frames in playback order.
+    /// * `loop_mode` - What happens after the last frame.
+    pub fn new(frames: Vec<Frame>, loop_mode: LoopMode) -> Self {
+        Self { frames, loop_mode }
+    }
+
+    /// Returns the frames in playback order.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Returns `true` if the timeline has no frames.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns the order in which frames are visited during one cycle.
+    ///
+    /// For [`LoopMode::PingPong`] the cycle goes forward and then back, without repeating the
+    /// first and last frames.
+    fn cycle(&self) -> Vec<usize> {
+        let forward = 0..self.frames.len();
+        match self.loop_mode {
+            LoopMode::PingPong if self.frames.len() > 2 => forward
+                .clone()
+                .chain((1..self.frames.len() - 1).rev())
+                .collect(),
+            _ => forward.collect(),
+        }
+    }
+
+    /// Duration of one cycle over the frames, including the way back for
+    /// [`LoopMode::PingPong`].
+    pub fn cycle_duration(&self) -> Duration {
+        self.cycle().iter().map(|&i| self.frames[i].duration).sum()
+    }
+
+    /// Returns the index of the frame shown `elapsed` after the timeline started.
+    ///
+    /// [`LoopMode::Once`] timelines hold their last frame once they are done.
+    pub fn frame_index(&self, elapsed: Duration) -> usize {
+        let cycle = self.cycle();
+        let period = self.cycle_duration();
+        if period.is_zero() {
+            return 0;
+        }
+
+        let mut time = match self.loop_mode {
+            LoopMode::Once if elapsed >= period => return self.frames.len() - 1,
+            LoopMode::Once => elapsed,
+            LoopMode::Loop | LoopMode::PingPong => {
+                Duration::from_nanos((elapsed.as_nanos() % period.as_nanos()) as u64)
+            }
+        };
+
+        for &i in &cycle {
+            if time < self.frames[i].duration {
+                return i;
+            }
+            time -= self.frames[i].duration;
+        }
+        cycle[cycle.len() - 1]
+    }
+
+    /// Returns the frame shown `elapsed` after the timeline started.
+    pub fn frame(&self, elapsed: Duration) -> &Frame {
+        &self.frames[self.frame_index(elapsed)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Timeline of three frames lasting 100ms each.
+    fn timeline(loop_mode: LoopMode) -> Timeline {
+        let frame = Frame {
+            image: None,
+            src: Rect::one(),
+            duration: Duration::from_millis(100),
+        };
+        Timeline::new(vec![frame; 3], loop_mode)
+    }
+
+    fn indices(timeline: &Timeline, millis: &[u64]) -> Vec<usize> {
+        millis
+            .iter()
+            .map(|&ms| timeline.frame_index(Duration::from_millis(ms)))
+            .collect()
+    }
+
+    #[test]
+    fn loop_starts_over() {
+        let timeline = timeline(LoopMode::Loop);
+        assert_eq!(indices(&timeline, &[0, 150, 250, 350]), [0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn once_holds_the_last_frame() {
+        let timeline = timeline(LoopMode::Once);
+        assert_eq!(indices(&timeline, &[0, 150, 350, 10_000]), [0, 1, 2, 2]);
+    }
+
+    #[test]
+    fn pingpong_plays_back_without_repeating_ends() {
+        let timeline = timeline(LoopMode::PingPong);
+        assert_eq!(timeline.cycle_duration(), Duration::from_millis(400));
+        assert_eq!(
+            indices(&timeline, &[0, 150, 250, 350, 450]),
+            [0, 1, 2, 1, 0]
+        );
+    }
+}
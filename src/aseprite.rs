@@ -0,0 +1,417 @@
+//! Module `aseprite` imports animations from Aseprite's "Export Sprite Sheet" JSON data.
+//!
+//! Aseprite writes a sprite sheet image together with a JSON file describing where every frame
+//! lives on the sheet, how long it is shown and which frames belong to which tag. Both the
+//! "Hash" and the "Array" layouts of the JSON data are supported.
+//!
+//! Each tag becomes an animation: its frames are taken in the tag's direction, and `pingpong`
+//! tags use [`LoopMode::PingPong`].
+//!
+//! Trimmed exports are rejected, since their frames would have to be drawn at an offset.
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{
+    Deserialize, Deserializer,
+    de::{MapAccess, Visitor},
+};
+
+use crate::{
+    companion::{AnimationConfig, LoopMode, Sprite, SpriteRect},
+    errors::CompanionError,
+};
+
+/// Parsed JSON data of an Aseprite sprite sheet export.
+#[derive(Debug, Deserialize)]
+pub struct AsepriteExport {
+    /// Frames of the sprite in timeline order.
+    frames: Frames,
+    /// Sheet image and tags.
+    meta: Meta,
+}
+
+/// Frame list, either as an array or as a hash keyed by frame file name.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Frames {
+    Array(Vec<Frame>),
+    Hash(OrderedFrames),
+}
+
+/// Frames of the "Hash" layout, kept in the order they appear in the file.
+#[derive(Debug)]
+struct OrderedFrames(Vec<Frame>);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Frame {
+    frame: SpriteRect,
+    duration: u64,
+    #[serde(default)]
+    trimmed: bool,
+    /// Part of the untrimmed frame the trimmed frame was cut from.
+    #[serde(default)]
+    sprite_source_size: Option<SpriteRect>,
+    /// Size of the untrimmed frame.
+    #[serde(default)]
+    source_size: Option<Size>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+struct Size {
+    w: u32,
+    h: u32,
+}
+
+impl Frame {
+    /// Returns `true` if the frame was cut down from a larger one when exporting.
+    fn is_trimmed(&self) -> bool {
+        let cut = match (self.sprite_source_size, self.source_size) {
+            (Some(part), Some(size)) => {
+                part.x != 0 || part.y != 0 || part.w != size.w || part.h != size.h
+            }
+            _ => false,
+        };
+        self.trimmed || cut
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Meta {
+    image: String,
+    #[serde(rename = "frameTags", default)]
+    frame_tags: Vec<FrameTag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FrameTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: Direction,
+    /// How often the tag plays, as a string. Missing or `"0"` means forever.
+    #[serde(default)]
+    repeat: Option<String>,
+}
+
+/// Playback direction of a tag.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Direction {
+    #[default]
+    Forward,
+    Reverse,
+    Pingpong,
+    PingpongReverse,
+}
+
+impl<'de> Deserialize<'de> for OrderedFrames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FramesVisitor;
+
+        impl<'de> Visitor<'de> for FramesVisitor {
+            type Value = OrderedFrames;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of frame names to frames")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut frames = Vec::new();
+                while let Some((_, frame)) = map.next_entry::<String, Frame>()? {
+                    frames.push(frame);
+                }
+                Ok(OrderedFrames(frames))
+            }
+        }
+
+        deserializer.deserialize_map(FramesVisitor)
+    }
+}
+
+/// Animation in `companion.toml` taken from an Aseprite export.
+///
+/// ```toml
+/// walk = { aseprite = "niko.json", tag = "walk" }
+/// ```
+///
+/// Without a `tag` all frames of the export are played in order. `loop` overrides the loop mode
+/// derived from the tag.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AsepriteSource {
+    /// Path to the JSON data, relative to the companion's directory.
+    pub aseprite: String,
+    /// Name of the tag to play.
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Loop mode to use instead of the one derived from the tag.
+    #[serde(rename = "loop", default)]
+    pub loop_mode: Option<LoopMode>,
+}
+
+impl AsepriteExport {
+    /// Reads and parses an export's JSON data.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the JSON file.
+    ///
+    /// # Returns
+    /// * `Ok(AsepriteExport)` if the file could be read and parsed.
+    /// * `Err(CompanionError)` if it is missing or isn't Aseprite JSON data.
+    pub fn load(path: &Path) -> Result<Self, CompanionError> {
+        let text = fs::read_to_string(path).map_err(|source| CompanionError::MissingFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        serde_json::from_str(&text).map_err(|e| CompanionError::BadAseprite {
+            path: path.to_path_buf(),
+            reason: e.to_string(),
+        })
+    }
+
+    fn frames(&self) -> &[Frame] {
+        match &self.frames {
+            Frames::Array(frames) => frames,
+            Frames::Hash(OrderedFrames(frames)) => frames,
+        }
+    }
+
+    /// Builds the animation described by `source`.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the JSON file, used in errors.
+    /// * `source` - Animation entry from `companion.toml`.
+    ///
+    /// # Returns
+    /// * `Ok(AnimationConfig)` with sprites pointing into the exported sheet. The sheet's path is
+    ///   relative to the companion's directory, like every other sprite.
+    /// * `Err(CompanionError)` if the tag doesn't exist, refers to missing frames or its frames
+    ///   are trimmed.
+    pub fn animation(
+        &self,
+        path: &Path,
+        source: &AsepriteSource,
+    ) -> Result<AnimationConfig, CompanionError> {
+        let error = |reason: String| CompanionError::BadAseprite {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let frames = self.frames();
+
+        let (range, direction, repeat) = match &source.tag {
+            Some(name) => {
+                let tag = self
+                    .meta
+                    .frame_tags
+                    .iter()
+                    .find(|tag| &tag.name == name)
+                    .ok_or_else(|| error(format!("no tag named `{name}`")))?;
+                if tag.from > tag.to || tag.to >= frames.len() {
+                    return Err(error(format!(
+                        "tag `{name}` spans frames {}..={}, but there are only {} frames",
+                        tag.from,
+                        tag.to,
+                        frames.len()
+                    )));
+                }
+                (tag.from..=tag.to, tag.direction, tag.repeat.as_deref())
+            }
+            None if frames.is_empty() => return Err(error("export has no frames".to_string())),
+            None => (0..=frames.len() - 1, Direction::Forward, None),
+        };
+
+        if let Some(index) = range.clone().find(|&index| frames[index].is_trimmed()) {
+            return Err(error(format!(
+                "frame {index} is trimmed, export the sprite sheet again without \"Trim Sprite\" \
+                 and \"Trim Cels\""
+            )));
+        }
+
+        // the sheet is written next to the JSON data
+        let sheet: PathBuf = Path::new(&source.aseprite)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(&self.meta.image);
+        let sheet = sheet.to_string_lossy().into_owned();
+
+        let mut sprites: Vec<Sprite> = frames[range]
+            .iter()
+            .map(|frame| Sprite {
+                path: sheet.clone(),
+                duration_ms: frame.duration,
+                rect: Some(frame.frame),
+            })
+            .collect();
+        if matches!(direction, Direction::Reverse | Direction::PingpongReverse) {
+            sprites.reverse();
+        }
+
+        let loop_mode = source.loop_mode.unwrap_or(match (direction, repeat) {
+            (_, Some("1")) => LoopMode::Once,
+            (Direction::Pingpong | Direction::PingpongReverse, _) => LoopMode::PingPong,
+            _ => LoopMode::Loop,
+        });
+
+        Ok(AnimationConfig {
+            frames: sprites,
+            loop_mode,
+            aseprite: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frame `index` of a 32x32 sheet row, shown for `index + 1` times 100ms.
+    fn frame(index: u32, trimmed: bool) -> String {
+        let (x, w) = if trimmed { (4, 24) } else { (0, 32) };
+        format!(
+            r#"{{ "frame": {{ "x": {}, "y": 0, "w": {w}, "h": 32 }}, "trimmed": {trimmed},
+                "spriteSourceSize": {{ "x": {x}, "y": 0, "w": {w}, "h": 32 }},
+                "sourceSize": {{ "w": 32, "h": 32 }}, "duration": {} }}"#,
+            index * 32,
+            (index + 1) * 100
+        )
+    }
+
+    /// Export of three frames in the "Array" layout with the given tags.
+    fn export(tags: &str) -> AsepriteExport {
+        let frames: Vec<String> = (0..3).map(|index| frame(index, false)).collect();
+        let json = format!(
+            r#"{{ "frames": [{}], "meta": {{ "image": "niko.png", "frameTags": [{tags}] }} }}"#,
+            frames.join(",")
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    fn source(tag: Option<&str>) -> AsepriteSource {
+        AsepriteSource {
+            aseprite: "sprites/niko.json".to_string(),
+            tag: tag.map(str::to_string),
+            loop_mode: None,
+        }
+    }
+
+    fn tagged(direction: &str, repeat: Option<&str>) -> AnimationConfig {
+        let repeat = repeat.map_or(String::new(), |r| format!(r#", "repeat": "{r}""#));
+        export(&format!(
+            r#"{{ "name": "walk", "from": 0, "to": 2, "direction": "{direction}"{repeat} }}"#
+        ))
+        .animation(Path::new("niko.json"), &source(Some("walk")))
+        .unwrap()
+    }
+
+    /// Returns the `x` of every frame's region, identifying the frame.
+    fn xs(animation: &AnimationConfig) -> Vec<u32> {
+        animation
+            .frames
+            .iter()
+            .map(|sprite| sprite.rect.unwrap().x)
+            .collect()
+    }
+
+    #[test]
+    fn hash_and_array_layouts_give_the_same_frames() {
+        let frames: Vec<String> = (0..3)
+            .map(|index| format!(r#""niko {index}.aseprite": {}"#, frame(index, false)))
+            .collect();
+        let hash: AsepriteExport = serde_json::from_str(&format!(
+            r#"{{ "frames": {{ {} }}, "meta": {{ "image": "niko.png" }} }}"#,
+            frames.join(",")
+        ))
+        .unwrap();
+
+        for export in [hash, export("")] {
+            let animation = export
+                .animation(Path::new("niko.json"), &source(None))
+                .unwrap();
+            assert_eq!(xs(&animation), [0, 32, 64]);
+            let durations: Vec<u64> = animation.frames.iter().map(|s| s.duration_ms).collect();
+            assert_eq!(durations, [100, 200, 300]);
+            assert_eq!(animation.frames[0].path, "sprites/niko.png");
+            assert_eq!(animation.loop_mode, LoopMode::Loop);
+        }
+    }
+
+    #[test]
+    fn tag_directions() {
+        let forward = tagged("forward", None);
+        assert_eq!(
+            (xs(&forward), forward.loop_mode),
+            (vec![0, 32, 64], LoopMode::Loop)
+        );
+
+        let reverse = tagged("reverse", None);
+        assert_eq!(
+            (xs(&reverse), reverse.loop_mode),
+            (vec![64, 32, 0], LoopMode::Loop)
+        );
+
+        let pingpong = tagged("pingpong", None);
+        assert_eq!(
+            (xs(&pingpong), pingpong.loop_mode),
+            (vec![0, 32, 64], LoopMode::PingPong)
+        );
+
+        let pingpong_reverse = tagged("pingpong_reverse", None);
+        assert_eq!(
+            (xs(&pingpong_reverse), pingpong_reverse.loop_mode),
+            (vec![64, 32, 0], LoopMode::PingPong)
+        );
+    }
+
+    #[test]
+    fn tag_repeat() {
+        assert_eq!(tagged("forward", Some("1")).loop_mode, LoopMode::Once);
+        assert_eq!(tagged("pingpong", Some("1")).loop_mode, LoopMode::Once);
+        assert_eq!(tagged("forward", Some("0")).loop_mode, LoopMode::Loop);
+        assert_eq!(tagged("pingpong", Some("3")).loop_mode, LoopMode::PingPong);
+    }
+
+    #[test]
+    fn loop_overrides_the_tag() {
+        let export = export(r#"{ "name": "walk", "from": 1, "to": 2, "direction": "pingpong" }"#);
+        let mut source = source(Some("walk"));
+        source.loop_mode = Some(LoopMode::Once);
+
+        let animation = export.animation(Path::new("niko.json"), &source).unwrap();
+
+        assert_eq!(xs(&animation), [32, 64]);
+        assert_eq!(animation.loop_mode, LoopMode::Once);
+    }
+
+    #[test]
+    fn unknown_tag_is_an_error() {
+        let export = export(r#"{ "name": "walk", "from": 0, "to": 2 }"#);
+
+        match export.animation(Path::new("niko.json"), &source(Some("run"))) {
+            Err(CompanionError::BadAseprite { path, reason }) => {
+                assert_eq!(path, Path::new("niko.json"));
+                assert_eq!(reason, "no tag named `run`");
+            }
+            other => panic!("expected an error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn trimmed_frames_are_rejected() {
+        let frames = [frame(0, false), frame(1, true)].join(",");
+        let export: AsepriteExport = serde_json::from_str(&format!(
+            r#"{{ "frames": [{frames}], "meta": {{ "image": "niko.png" }} }}"#
+        ))
+        .unwrap();
+
+        match export.animation(Path::new("niko.json"), &source(None)) {
+            Err(CompanionError::BadAseprite { reason, .. }) => {
+                assert!(reason.starts_with("frame 1 is trimmed"), "{reason}");
+            }
+            other => panic!("expected an error, got {other:?}"),
+        }
+    }
+}
@@ -5,15 +5,26 @@
 //! - Timing updates to avoid rapid behavior changes
 //! - Picking behaviors based on transition weights
 //!
-//! # Safety
-//! This module uses raw pointers to `winit::Window`. They must remain valid while
-//! the manager exists.
+//! The manager doesn't touch the window itself, it is handed an [`Environment`] snapshot of the
+//! companion's surroundings on every update instead.
 
-use std::time::Instant;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use crate::clock::SharedClock;
 
-use ggez::winit;
 use log::debug;
-use rand::distr::{Distribution, weighted::WeightedIndex};
+use rand::{
+    SeedableRng,
+    distr::{Distribution, weighted::WeightedIndex},
+    rngs::StdRng,
+};
+use serde::Deserialize;
 
 /// Possible behaviors for the companion character.
 ///
@@ -22,7 +33,8 @@ use rand::distr::{Distribution, weighted::WeightedIndex};
 /// - `WalkLeft` / `WalkRight`: moving horizontally
 /// - `Fall`: fall out from top of the screen, only if companion is at desired position
 /// - `Jump`: self-explanatory
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Behavior {
     Idle,
     WalkLeft,
@@ -31,72 +43,230 @@ pub enum Behavior {
     Jump,
 }
 
+impl Behavior {
+    /// All behaviors, in declaration order.
+    pub const ALL: [Behavior; 5] = [
+        Behavior::Idle,
+        Behavior::WalkLeft,
+        Behavior::WalkRight,
+        Behavior::Fall,
+        Behavior::Jump,
+    ];
+
+    /// Name of the behavior as written in `companion.toml`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Behavior::Idle => "idle",
+            Behavior::WalkLeft => "walk_left",
+            Behavior::WalkRight => "walk_right",
+            Behavior::Fall => "fall",
+            Behavior::Jump => "jump",
+        }
+    }
+}
+
+impl fmt::Display for Behavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Behavior {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Behavior::ALL
+            .into_iter()
+            .find(|behavior| behavior.name() == s)
+            .ok_or_else(|| format!("unknown behavior `{s}`"))
+    }
+}
+
 /// Manages the companion's behavior state.
 ///
 /// Tracks the current and previous [`Behavior`] and updates them based on
-/// transition probabilities. Updates occur at intervals to prevent rapid changes.
-///
-/// # Safety
-/// The `window` pointer must remain valid for the lifetime of the manager.
+/// the companion's [`TransitionTable`]. Updates occur at intervals to prevent rapid changes.
 pub struct BehaviorManager {
     current: Option<Behavior>,
     previous: Option<Behavior>,
     last_change: Instant,
-    window: *const winit::window::Window,
+    transitions: TransitionTable,
+    rng: SharedRng,
+    clock: SharedClock,
+}
+
+/// Snapshot of the companion's surroundings, passed to [`BehaviorManager::update`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Environment {
+    /// Position of the companion's top left corner on the screen.
+    pub position: (f32, f32),
+
+    /// Size of the companion.
+    pub size: (f32, f32),
+
+    /// Size of the screen the companion is on.
+    pub screen: (f32, f32),
+
+    /// Position of the mouse cursor on the screen, if known.
+    pub cursor: Option<(f32, f32)>,
+}
+
+/// Random number generator shared by everything random about a companion, so one seed
+/// reproduces all of its behavior.
+pub type SharedRng = Arc<Mutex<StdRng>>;
+
+/// Creates the [`SharedRng`] of a companion from `seed`.
+pub fn seeded_rng(seed: u64) -> SharedRng {
+    Arc::new(Mutex::new(StdRng::seed_from_u64(seed)))
 }
 
-/// Simplified type for [`TRANSITIONS`]
-type TransitionType = &'static [(Option<Behavior>, &'static [(Behavior, f32)])];
+/// Simplified type for a row of weighted candidates in [`TransitionTable`].
+type Weights = Vec<(Behavior, f32)>;
 
-/// Maps a previous [`Behavior`] to weighted probabilities for the next behavior.
+/// Key used in `companion.toml` for the row picked when there is no previous behavior.
+pub const INITIAL_STATE: &str = "initial";
+
+/// Weighted transition matrix used to pick the next [`Behavior`].
+///
+/// Each row maps a previous behavior (or `None` for the initial state) to the
+/// `(Behavior, weight)` pairs that may follow it. Rows are declared per companion under
+/// `[transitions]` in `companion.toml`:
+///
+/// ```toml
+/// [transitions]
+/// initial = { idle = 0.25, walk_left = 0.25, walk_right = 0.25, jump = 0.15 }
+/// idle = { idle = 0.2, walk_left = 0.3, walk_right = 0.3, jump = 0.1 }
+/// ```
 ///
-/// Each entry contains:
-/// - An `Option<Behavior>` representing the previous state (or `None` for initial state)
-/// - A slice of `(Behavior, weight)` tuples representing possible next behaviors and their relative probabilities
-static TRANSITIONS: TransitionType = &[
-    (
-        Some(Behavior::Idle),
-        &[
-            (Behavior::Idle, 0.2),
-            (Behavior::WalkLeft, 0.3),
-            (Behavior::WalkRight, 0.3),
-            (Behavior::Jump, 0.1),
-        ],
-    ),
-    (
-        Some(Behavior::WalkLeft),
-        &[
-            (Behavior::Idle, 0.4),
-            (Behavior::WalkLeft, 0.1),
-            (Behavior::WalkRight, 0.3),
-            (Behavior::Jump, 0.1),
-        ],
-    ),
-    (
-        None,
-        &[
-            (Behavior::Idle, 0.25),
-            (Behavior::WalkLeft, 0.25),
-            (Behavior::WalkRight, 0.25),
-            (Behavior::Jump, 0.15),
-        ],
-    ),
-];
+/// Previous behaviors without a row of their own fall back to the `initial` row.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "BTreeMap<String, BTreeMap<Behavior, f32>>")]
+pub struct TransitionTable {
+    rows: Vec<(Option<Behavior>, Weights)>,
+    initial: Weights,
+}
+
+impl TransitionTable {
+    /// Returns the weighted candidates that may follow `previous`.
+    pub fn weights_for(&self, previous: Option<Behavior>) -> &[(Behavior, f32)] {
+        self.rows
+            .iter()
+            .find(|(state, _)| *state == previous)
+            .map(|(_, weights)| weights.as_slice())
+            .unwrap_or(&self.initial)
+    }
+
+    /// Checks a single row of a transition matrix read from `companion.toml`.
+    ///
+    /// # Arguments
+    /// * `state` - Key of the row, a behavior or `initial`.
+    /// * `weights` - Candidates of the row with their weights.
+    ///
+    /// # Returns
+    /// * `Ok(Some(behavior))` for the row of `behavior`, `Ok(None)` for the `initial` row.
+    /// * `Err(String)` if `state` is unknown, the row has no candidates or one of the weights
+    ///   isn't a positive finite number.
+    pub fn check_row(
+        state: &str,
+        weights: &BTreeMap<Behavior, f32>,
+    ) -> Result<Option<Behavior>, String> {
+        if weights.is_empty() {
+            return Err(format!("transition row `{state}` has no behaviors"));
+        }
+        if let Some((behavior, weight)) = weights
+            .iter()
+            .find(|(_, weight)| !weight.is_finite() || **weight <= 0.0)
+        {
+            return Err(format!(
+                "transition `{state}` -> `{behavior}` has weight {weight}, weights must be positive"
+            ));
+        }
+
+        if state == INITIAL_STATE {
+            Ok(None)
+        } else {
+            state.parse::<Behavior>().map(Some)
+        }
+    }
+}
+
+impl TryFrom<BTreeMap<String, BTreeMap<Behavior, f32>>> for TransitionTable {
+    type Error = String;
+
+    /// Validates a transition matrix read from `companion.toml`.
+    ///
+    /// Every row key must be a known behavior or `initial`, every row must have at least one
+    /// candidate, all weights must be positive finite numbers and the `initial` row is required.
+    fn try_from(map: BTreeMap<String, BTreeMap<Behavior, f32>>) -> Result<Self, Self::Error> {
+        let mut rows = Vec::new();
+        let mut initial = None;
+
+        for (state, weights) in map {
+            let row = Self::check_row(&state, &weights)?;
+            let weights: Weights = weights.into_iter().collect();
+            match row {
+                Some(behavior) => rows.push((Some(behavior), weights)),
+                None => initial = Some(weights),
+            }
+        }
+
+        let initial =
+            initial.ok_or_else(|| format!("transitions are missing the `{INITIAL_STATE}` row"))?;
+        Ok(Self { rows, initial })
+    }
+}
+
+impl Default for TransitionTable {
+    /// Built-in matrix used by companions that don't declare `[transitions]`.
+    fn default() -> Self {
+        Self {
+            rows: vec![
+                (
+                    Some(Behavior::Idle),
+                    vec![
+                        (Behavior::Idle, 0.2),
+                        (Behavior::WalkLeft, 0.3),
+                        (Behavior::WalkRight, 0.3),
+                        (Behavior::Jump, 0.1),
+                    ],
+                ),
+                (
+                    Some(Behavior::WalkLeft),
+                    vec![
+                        (Behavior::Idle, 0.4),
+                        (Behavior::WalkLeft, 0.1),
+                        (Behavior::WalkRight, 0.3),
+                        (Behavior::Jump, 0.1),
+                    ],
+                ),
+            ],
+            initial: vec![
+                (Behavior::Idle, 0.25),
+                (Behavior::WalkLeft, 0.25),
+                (Behavior::WalkRight, 0.25),
+                (Behavior::Jump, 0.15),
+            ],
+        }
+    }
+}
 
 impl BehaviorManager {
-    /// Creates a new [`BehaviorManager'] associated with the companions [`winit::winit::Window`].
+    /// Creates a new [`BehaviorManager`].
     ///
     /// Initially, both `current` and `previous` are None.
     ///
-    /// # Safety
-    /// The caller must ensure that the provided [`winit::winit::Window`] pointer is non-null and
-    /// remains valid for the lifetime of the manager
-    pub fn new(window: *const winit::window::Window) -> Self {
+    /// # Arguments
+    /// * `transitions` - Transition matrix used to pick behaviors.
+    /// * `rng` - Source of randomness for picking behaviors.
+    /// * `clock` - Source of the current time.
+    pub fn new(transitions: TransitionTable, rng: SharedRng, clock: SharedClock) -> Self {
         Self {
             current: None,
             previous: None,
-            last_change: Instant::now(),
-            window,
+            last_change: clock.now(),
+            transitions,
+            rng,
+            clock,
         }
     }
 
@@ -107,17 +277,16 @@ impl BehaviorManager {
     /// - more than 10 seconds have elapsed since the last change.
     ///
     /// Otherwise, returns `None`.
-    pub fn update(&mut self) -> Option<Behavior> {
-        if self.current.is_none() || self.last_change.elapsed().as_secs_f32() > 10.0 {
+    ///
+    /// # Arguments
+    /// * `environment` - Current surroundings of the companion.
+    pub fn update(&mut self, environment: &Environment) -> Option<Behavior> {
+        if self.current.is_none() || self.clock.elapsed(self.last_change).as_secs_f32() > 10.0 {
             self.previous = self.current;
             self.current = Some(self.pick_behavior_random());
-            self.last_change = Instant::now();
+            self.last_change = self.clock.now();
 
-            debug!(
-                "ima behave >:3 {:?} also have this window size so i could test my pOiNtErS {:?}",
-                self.current,
-                unsafe { (*self.window).outer_size() }
-            );
+            debug!("ima behave >:3 {:?} in {environment:?}", self.current);
 
             return self.current;
         }
@@ -129,14 +298,134 @@ impl BehaviorManager {
     ///
     /// Called internally by [`update`].
     fn pick_behavior_random(&mut self) -> Behavior {
-        let weights = TRANSITIONS
-            .iter()
-            .find(|(state, _)| *state == self.previous)
-            .map(|(_, probs)| *probs)
-            .unwrap_or_else(|| TRANSITIONS.last().unwrap().1);
+        let weights = self.transitions.weights_for(self.previous);
 
-        let mut rng = rand::rng();
         let dist = WeightedIndex::new(weights.iter().map(|(_, w)| *w)).unwrap();
-        weights[dist.sample(&mut rng)].0
+        weights[dist.sample(&mut *self.rng.lock().unwrap())].0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn behavior_changes_every_ten_seconds() {
+        let clock = Arc::new(ManualClock::new());
+        let mut manager =
+            BehaviorManager::new(TransitionTable::default(), seeded_rng(1), clock.clone());
+        let environment = Environment {
+            position: (0.0, 0.0),
+            size: (96.0, 96.0),
+            screen: (1920.0, 1080.0),
+            cursor: None,
+        };
+
+        assert!(manager.update(&environment).is_some(), "first update picks");
+        clock.advance(Duration::from_secs(9));
+        assert_eq!(manager.update(&environment), None);
+        clock.advance(Duration::from_secs(2));
+        assert!(manager.update(&environment).is_some());
+        assert_eq!(manager.update(&environment), None);
+    }
+
+    fn table(rows: &[(&str, &[(Behavior, f32)])]) -> Result<TransitionTable, String> {
+        let map: BTreeMap<String, BTreeMap<Behavior, f32>> = rows
+            .iter()
+            .map(|(state, weights)| (state.to_string(), weights.iter().copied().collect()))
+            .collect();
+        TransitionTable::try_from(map)
+    }
+
+    fn environment() -> Environment {
+        Environment {
+            position: (0.0, 0.0),
+            size: (96.0, 96.0),
+            screen: (1920.0, 1080.0),
+            cursor: None,
+        }
+    }
+
+    #[test]
+    fn transition_weights_must_be_positive_and_finite() {
+        for weight in [0.0, -1.0, f32::INFINITY, f32::NAN] {
+            let error = table(&[(
+                "initial",
+                &[(Behavior::Idle, 1.0), (Behavior::Jump, weight)],
+            )])
+            .unwrap_err();
+            assert!(error.contains("`initial` -> `jump`"), "{error}");
+        }
+    }
+
+    #[test]
+    fn transition_rows_must_not_be_empty() {
+        let error = table(&[("initial", &[(Behavior::Idle, 1.0)]), ("idle", &[])]).unwrap_err();
+        assert_eq!(error, "transition row `idle` has no behaviors");
+    }
+
+    #[test]
+    fn transitions_need_an_initial_row() {
+        let error = table(&[("idle", &[(Behavior::Idle, 1.0)])]).unwrap_err();
+        assert_eq!(error, "transitions are missing the `initial` row");
+    }
+
+    #[test]
+    fn transitions_only_know_behaviors() {
+        let error = table(&[
+            ("initial", &[(Behavior::Idle, 1.0)]),
+            ("dance", &[(Behavior::Idle, 1.0)]),
+        ])
+        .unwrap_err();
+        assert_eq!(error, "unknown behavior `dance`");
+
+        let target = serde_json::from_str::<TransitionTable>(r#"{ "initial": { "dance": 1.0 } }"#);
+        assert!(target.is_err());
+    }
+
+    #[test]
+    fn rows_without_a_row_of_their_own_use_initial() {
+        let table = table(&[
+            ("initial", &[(Behavior::Idle, 1.0)]),
+            ("idle", &[(Behavior::Jump, 1.0)]),
+        ])
+        .unwrap();
+        let clock = Arc::new(ManualClock::new());
+        let mut manager = BehaviorManager::new(table, seeded_rng(3), clock.clone());
+
+        let mut picks = Vec::new();
+        for _ in 0..3 {
+            picks.extend(manager.update(&environment()));
+            clock.advance(Duration::from_secs(11));
+        }
+
+        assert_eq!(picks, [Behavior::Idle, Behavior::Jump, Behavior::Idle]);
+    }
+
+    #[test]
+    fn picks_follow_the_weights() {
+        let table = table(&[(
+            "initial",
+            &[(Behavior::Idle, 3.0), (Behavior::WalkLeft, 1.0)],
+        )])
+        .unwrap();
+        let clock = Arc::new(ManualClock::new());
+        let mut manager = BehaviorManager::new(table, seeded_rng(5), clock.clone());
+
+        let mut idle = 0;
+        for _ in 0..4000 {
+            if manager.update(&environment()) == Some(Behavior::Idle) {
+                idle += 1;
+            }
+            clock.advance(Duration::from_secs(11));
+        }
+
+        assert!(
+            (2800..3200).contains(&idle),
+            "idled {idle} times out of 4000"
+        );
     }
 }
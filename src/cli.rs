@@ -0,0 +1,132 @@
+//! Command-line interface of the application.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::{Parser, Subcommand};
+use log::LevelFilter;
+
+use crate::simulate::Format;
+
+/// Desktop companion inspired by Shimeji.
+///
+/// Without a subcommand every companion from `config.toml` is started in its own process.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Path to `config.toml` or the directory containing it.
+    ///
+    /// Defaults to searching `$XDG_CONFIG_HOME/desktop-companion`,
+    /// `/usr/share/desktop-companion` and `./config`.
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Maximum level of log messages (off, error, warn, info, debug, trace).
+    #[arg(long, global = true, default_value_t = LevelFilter::Debug)]
+    pub log_level: LevelFilter,
+
+    /// Seed for the companion's RNG, for reproducing its behavior. A random seed is logged
+    /// at startup otherwise.
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run a single companion in this process.
+    Run {
+        /// Name of the companion to run. Defaults to the first one in `config.toml`.
+        #[arg(long)]
+        companion: Option<String>,
+    },
+    /// List the companions configured in `config.toml`.
+    List,
+    /// Check `config.toml` and every companion pack for problems without opening a window.
+    ///
+    /// Reports all problems at once and exits with code 1 if there are any, so it can be used
+    /// in CI for asset repositories.
+    Validate,
+    /// Convert a Shimeji image set into a companion.
+    ///
+    /// Writes `companion.toml` and the used images to OUTPUT, then prints the `[[companion]]`
+    /// entry to add to `config.toml`.
+    ImportShimeji {
+        /// Directory containing `shime1.png` and the other images.
+        images: PathBuf,
+        /// Directory to write the companion to.
+        output: PathBuf,
+        /// Directory containing `actions.xml` and `behaviors.xml`. Searched next to and above
+        /// IMAGES by default.
+        #[arg(long)]
+        conf: Option<PathBuf>,
+        /// Name of the companion. Defaults to the name of OUTPUT.
+        #[arg(long)]
+        name: Option<String>,
+        /// Overwrite an existing `companion.toml` in OUTPUT.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Run a companion on a virtual screen, as fast as possible, without opening a window.
+    ///
+    /// Writes the behaviors picked to OUTPUT and prints how much time was spent in each, how
+    /// much of the screen was covered, and how often the companion hit the screen's edges.
+    /// Together with --seed this replays exactly what a companion did.
+    Simulate {
+        /// File to write the timeline to.
+        output: PathBuf,
+        /// Name of the companion to simulate. Defaults to the first one in `config.toml`.
+        #[arg(long)]
+        companion: Option<String>,
+        /// Simulated time, e.g. `24h` or `90min`.
+        #[arg(long, default_value = "24h", value_parser = humantime::parse_duration)]
+        duration: Duration,
+        /// Simulated time between two updates of the companion.
+        #[arg(long, default_value = "40ms", value_parser = parse_step)]
+        step: Duration,
+        /// Size of the virtual screen in pixels.
+        #[arg(long, default_value = "1920x1080", value_parser = parse_screen)]
+        screen: (f32, f32),
+        /// Format of OUTPUT. Defaults to JSON for `.json` files and CSV otherwise.
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+    },
+}
+
+/// Parses the step of a simulation, which must not be zero.
+fn parse_step(s: &str) -> Result<Duration, String> {
+    match humantime::parse_duration(s) {
+        Ok(step) if step.is_zero() => Err("the step must be longer than zero".to_string()),
+        Ok(step) => Ok(step),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Parses a screen size given as `WIDTHxHEIGHT`.
+fn parse_screen(s: &str) -> Result<(f32, f32), String> {
+    let invalid = || format!("`{s}` should look like `1920x1080`");
+    let (width, height) = s.split_once('x').ok_or_else(invalid)?;
+    match (width.trim().parse::<f32>(), height.trim().parse::<f32>()) {
+        (Ok(width), Ok(height)) if width > 0.0 && height > 0.0 => Ok((width, height)),
+        _ => Err(invalid()),
+    }
+}
+
+impl Cli {
+    /// Global options that child processes spawned by the supervisor should inherit.
+    ///
+    /// # Arguments
+    /// * `config_path` - Resolved path to `config.toml`, so children don't search again.
+    pub fn forwarded_args(&self, config_path: &Path) -> Vec<String> {
+        vec![
+            "--config".to_string(),
+            config_path.display().to_string(),
+            "--log-level".to_string(),
+            self.log_level.to_string(),
+        ]
+    }
+}
@@ -3,9 +3,21 @@
 //! It provides structures representing companions, their sprites, and animation configurations,
 //! as well as functions to load configuration from TOML files.
 
-use config::{Config, ConfigError};
-use serde::Deserialize;
-use std::collections::HashMap;
+use config::{Config, Map, Value};
+use serde::{Deserialize, de::DeserializeOwned};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    animation::easing::Easing,
+    aseprite::{AsepriteExport, AsepriteSource},
+    behavior::{Behavior, TransitionTable},
+    errors::CompanionError,
+    physics::PhysicsConfig,
+};
 
 /// Root application configuration containing all companions.
 #[derive(Debug, Deserialize)]
@@ -27,49 +39,748 @@ pub struct Companion {
     pub height: f32,
     /// Walking speed of the companion.
     pub walkspeed: f32,
+    /// Height of the jump arc in pixels.
+    #[serde(default = "default_jump_height")]
+    pub jump_height: f32,
+    /// Horizontal distance covered by a jump in pixels.
+    #[serde(default = "default_jump_distance")]
+    pub jump_distance: f32,
+    /// Physical parameters used while falling, jumping or being dropped.
+    #[serde(default)]
+    pub physics: PhysicsConfig,
+}
+
+fn default_jump_height() -> f32 {
+    120.0
+}
+
+fn default_jump_distance() -> f32 {
+    150.0
+}
+
+/// A companion together with the directory holding its `companion.toml` and sprites.
+///
+/// All paths belonging to a companion are resolved through its pack, so the companion's display
+/// `name` never takes part in locating files.
+#[derive(Debug, Clone)]
+pub struct CompanionPack {
+    /// Metadata of the companion from `config.toml`.
+    pub companion: Companion,
+    /// Directory containing `companion.toml`.
+    root: PathBuf,
+}
+
+impl CompanionPack {
+    /// Creates the pack of `companion`.
+    ///
+    /// `companion.path` is resolved relative to the directory containing `config.toml`, unless
+    /// it is absolute.
+    ///
+    /// # Arguments
+    /// * `config_path` - Path to `config.toml`.
+    /// * `companion` - Companion entry from `config.toml`.
+    pub fn new(config_path: &Path, companion: Companion) -> Self {
+        let root = config_path
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join(&companion.path);
+        Self { companion, root }
+    }
+
+    /// Directory containing the companion's files.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path to the companion's `companion.toml`.
+    pub fn config_path(&self) -> PathBuf {
+        self.root.join("companion.toml")
+    }
+
+    /// Resolves a path from `companion.toml` relative to the pack's root.
+    ///
+    /// Absolute paths are returned unchanged.
+    pub fn asset_path(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// Loads the companion's `companion.toml`.
+    pub fn load_config(&self) -> Result<CompanionConfig, CompanionError> {
+        load_companion_config(&self.config_path())
+    }
+}
+
+impl AppConfig {
+    /// Returns the packs of all configured companions.
+    ///
+    /// # Arguments
+    /// * `config_path` - Path to the `config.toml` this configuration was loaded from.
+    pub fn packs(&self, config_path: &Path) -> Vec<CompanionPack> {
+        self.companion
+            .iter()
+            .map(|c| CompanionPack::new(config_path, c.clone()))
+            .collect()
+    }
 }
 
+/// How long a sprite is shown if it doesn't declare `duration_ms`.
+pub const DEFAULT_FRAME_DURATION_MS: u64 = 200;
+
 /// Single sprite for an animation.
+///
+/// Animated GIFs and APNGs expand into one frame per image, each shown for its embedded delay.
 #[derive(Debug, Deserialize, Clone)]
 pub struct Sprite {
     /// Path to the image file for the sprite.
     pub path: String,
+    /// How long the sprite is shown in milliseconds. Used by animated images only for frames
+    /// without a delay of their own.
+    #[serde(default = "default_frame_duration")]
+    pub duration_ms: u64,
+    /// Region of the image to show, or the whole image if omitted.
+    #[serde(default)]
+    pub rect: Option<SpriteRect>,
+}
+
+/// Rectangular region of an image in pixels.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct SpriteRect {
+    /// Left edge of the region.
+    pub x: u32,
+    /// Top edge of the region.
+    pub y: u32,
+    /// Width of the region.
+    pub w: u32,
+    /// Height of the region.
+    pub h: u32,
+}
+
+fn default_frame_duration() -> u64 {
+    DEFAULT_FRAME_DURATION_MS
+}
+
+/// What an animation does after its last frame.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LoopMode {
+    /// Start over from the first frame.
+    #[default]
+    Loop,
+    /// Stop on the last frame.
+    Once,
+    /// Play backwards to the first frame, then forwards again.
+    PingPong,
+}
+
+/// Frames of a single animation and how they are played.
+///
+/// In `companion.toml` an animation is either a plain list of sprites, which loops:
+///
+/// ```toml
+/// walk = [{ path = "walk1.png" }, { path = "walk2.png", duration_ms = 300 }]
+/// ```
+///
+/// or a table with the frames and a loop mode:
+///
+/// ```toml
+/// wave = { loop = "pingpong", frames = [{ path = "wave1.png" }, { path = "wave2.png" }] }
+/// ```
+///
+/// or regions of a single sprite sheet, either cut into a grid (`frames` picks cells in reading
+/// order and defaults to all of them):
+///
+/// ```toml
+/// walk = { sheet = "niko.png", frame_width = 96, frame_height = 128, columns = 4, rows = 2, frames = [0, 1, 2, 3] }
+/// ```
+///
+/// or given as explicit rectangles:
+///
+/// ```toml
+/// sit = { sheet = "niko.png", rects = [{ x = 0, y = 256, w = 96, h = 128 }] }
+/// ```
+///
+/// Sheet animations share `duration_ms` between all of their frames.
+///
+/// Finally, an animation can be a tag of an Aseprite export, see [`AsepriteSource`]:
+///
+/// ```toml
+/// walk = { aseprite = "niko.json", tag = "walk" }
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+#[serde(try_from = "AnimationSource")]
+pub struct AnimationConfig {
+    /// Sprites in playback order.
+    pub frames: Vec<Sprite>,
+    /// What happens after the last frame.
+    pub loop_mode: LoopMode,
+    /// Aseprite export the frames are still to be read from.
+    ///
+    /// Resolved into `frames` by [`read_companion_config`], since that needs the companion's
+    /// directory.
+    pub aseprite: Option<AsepriteSource>,
+}
+
+/// Forms an animation can be written in, see [`AnimationConfig`].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnimationSource {
+    Frames(Vec<Sprite>),
+    Sheet(SheetSource),
+    Aseprite(AsepriteSource),
+    Table {
+        frames: Vec<Sprite>,
+        #[serde(rename = "loop", default)]
+        loop_mode: LoopMode,
+    },
+}
+
+/// Animation cut out of a sprite sheet, see [`AnimationConfig`].
+#[derive(Deserialize)]
+struct SheetSource {
+    sheet: String,
+    frame_width: Option<u32>,
+    frame_height: Option<u32>,
+    columns: Option<u32>,
+    rows: Option<u32>,
+    frames: Option<Vec<u32>>,
+    rects: Option<Vec<SpriteRect>>,
+    #[serde(default = "default_frame_duration")]
+    duration_ms: u64,
+    #[serde(rename = "loop", default)]
+    loop_mode: LoopMode,
+}
+
+impl SheetSource {
+    /// Computes the regions of the sheet making up the animation.
+    fn rects(&self) -> Result<Vec<SpriteRect>, String> {
+        if let Some(rects) = &self.rects {
+            return Ok(rects.clone());
+        }
+
+        let (Some(w), Some(h), Some(columns)) = (self.frame_width, self.frame_height, self.columns)
+        else {
+            return Err(format!(
+                "sprite sheet `{}` needs either `rects` or `frame_width`, `frame_height` and `columns`",
+                self.sheet
+            ));
+        };
+        if w == 0 || h == 0 || columns == 0 {
+            return Err(format!(
+                "sprite sheet `{}` has an empty grid, frame size and columns must be positive",
+                self.sheet
+            ));
+        }
+
+        let cells = match (&self.frames, self.rows) {
+            (Some(frames), _) => frames.clone(),
+            (None, Some(rows)) => (0..columns * rows).collect(),
+            (None, None) => {
+                return Err(format!(
+                    "sprite sheet `{}` needs `rows` or a list of `frames`",
+                    self.sheet
+                ));
+            }
+        };
+
+        Ok(cells
+            .into_iter()
+            .map(|cell| SpriteRect {
+                x: cell % columns * w,
+                y: cell / columns * h,
+                w,
+                h,
+            })
+            .collect())
+    }
+}
+
+impl TryFrom<AnimationSource> for AnimationConfig {
+    type Error = String;
+
+    fn try_from(source: AnimationSource) -> Result<Self, Self::Error> {
+        Ok(match source {
+            AnimationSource::Frames(frames) => Self {
+                frames,
+                loop_mode: LoopMode::default(),
+                aseprite: None,
+            },
+            AnimationSource::Table { frames, loop_mode } => Self {
+                frames,
+                loop_mode,
+                aseprite: None,
+            },
+            AnimationSource::Sheet(sheet) => Self {
+                frames: sheet
+                    .rects()?
+                    .into_iter()
+                    .map(|rect| Sprite {
+                        path: sheet.sheet.clone(),
+                        duration_ms: sheet.duration_ms,
+                        rect: Some(rect),
+                    })
+                    .collect(),
+                loop_mode: sheet.loop_mode,
+                aseprite: None,
+            },
+            AnimationSource::Aseprite(aseprite) => Self {
+                frames: Vec::new(),
+                loop_mode: LoopMode::default(),
+                aseprite: Some(aseprite),
+            },
+        })
+    }
+}
+
+/// Short animation played now and then while idling.
+///
+/// Declared under `[fidgets]` in `companion.toml`, keyed by the name of the animation to play:
+///
+/// ```toml
+/// [fidgets]
+/// blink = { every = 4.0, jitter = 1.5 }
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct FidgetConfig {
+    /// Average number of seconds between two fidgets.
+    pub every: f32,
+    /// Maximum random deviation from `every` in seconds, in both directions.
+    #[serde(default)]
+    pub jitter: f32,
+}
+
+/// Step of a scripted sequence.
+///
+/// Sequences are declared under `[sequences]` in `companion.toml`, keyed by the behavior they
+/// replace, and play their steps one after another:
+///
+/// ```toml
+/// [sequences]
+/// walk_right = [
+///     { walk = "right_edge" },
+///     { play = "sit", seconds = 3.0 },
+///     { play = "wave" },
+///     { parallel = [{ walk = -100 }, { play = "wave" }] },
+/// ]
+/// ```
+///
+/// While it plays, a sequence is the animation `sequence:<behavior>`, e.g. in
+/// `[animation_transitions]`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum StepConfig {
+    /// Plays an animation in place, for `seconds` or one cycle of its frames.
+    Play {
+        /// Name of the animation.
+        play: String,
+        /// How long to play the animation in seconds.
+        #[serde(default)]
+        seconds: Option<f32>,
+    },
+    /// Walks at the companion's `walkspeed`.
+    Walk {
+        /// Where to walk to.
+        walk: WalkTarget,
+        /// Animation shown while walking, `walk` if omitted.
+        #[serde(default)]
+        animation: Option<String>,
+        /// Curve applied to the movement.
+        #[serde(default)]
+        easing: Easing,
+    },
+    /// Plays steps at the same time. Only the last one is drawn, so a movement can be paired with
+    /// the frames of another animation.
+    Parallel {
+        /// Steps to play together.
+        parallel: Vec<StepConfig>,
+    },
+}
+
+impl StepConfig {
+    /// Returns the names of the animations the step refers to.
+    pub fn animations(&self) -> Vec<&str> {
+        match self {
+            StepConfig::Play { play, .. } => vec![play.as_str()],
+            StepConfig::Walk { animation, .. } => vec![animation.as_deref().unwrap_or("walk")],
+            StepConfig::Parallel { parallel } => {
+                parallel.iter().flat_map(StepConfig::animations).collect()
+            }
+        }
+    }
 }
 
-/// Configuration for a single companion's animations.
+/// Destination of a walk in a sequence.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub enum WalkTarget {
+    /// One of the monitor's edges.
+    Edge(Edge),
+    /// Distance in pixels from where the walk starts, negative to walk left.
+    By(f32),
+}
+
+/// Side edge of the monitor.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Edge {
+    /// The left edge.
+    LeftEdge,
+    /// The right edge.
+    RightEdge,
+}
+
+/// Configuration for a single companion's animations and behavior.
 #[derive(Debug, Deserialize, Clone)]
 pub struct CompanionConfig {
-    /// Map of animation names to lists of sprites.
-    pub animations: HashMap<String, Vec<Sprite>>,
+    /// Map of animation names to their frames.
+    pub animations: HashMap<String, AnimationConfig>,
+    /// Fidgets played while idling, keyed by animation name. Ordered, so fidgets draw from the
+    /// companion's RNG in the same order on every run.
+    #[serde(default)]
+    pub fidgets: BTreeMap<String, FidgetConfig>,
+    /// Behavior transition matrix. Falls back to the built-in one when omitted.
+    #[serde(default)]
+    pub transitions: TransitionTable,
+    /// Easing of the movement of each behavior, linear when omitted.
+    #[serde(default)]
+    pub easing: HashMap<Behavior, Easing>,
+    /// Scripted sequences played instead of the built-in action of a behavior.
+    #[serde(default)]
+    pub sequences: HashMap<Behavior, Vec<StepConfig>>,
+    /// Animations played when switching between two animations, keyed by `"from->to"`:
+    ///
+    /// ```toml
+    /// [animation_transitions]
+    /// "walk->idle" = "stop"
+    /// ```
+    #[serde(default)]
+    pub animation_transitions: HashMap<String, String>,
+    /// How long the previous animation fades out when switching to another one without
+    /// transition frames, in milliseconds. `0` switches instantly.
+    #[serde(default = "default_crossfade")]
+    pub crossfade_ms: u64,
 }
 
-/// Loads the main application configuration from `./config/config.toml`.
+fn default_crossfade() -> u64 {
+    150
+}
+
+/// Splits a key of `[animation_transitions]` into the names of both animations.
 ///
 /// # Returns
-/// * `Ok(AppConfig)` if the file exists and deserializes successfully.
-/// * `Err(ConfigError)` if the file cannot be read or deserialized.
-pub fn load_config() -> Result<AppConfig, ConfigError> {
-    let settings = Config::builder()
-        .add_source(config::File::with_name("./config/config.toml"))
+/// `Some((from, to))` for keys of the form `"from->to"`, `None` otherwise.
+pub fn split_transition_key(key: &str) -> Option<(&str, &str)> {
+    let (from, to) = key.split_once("->")?;
+    let (from, to) = (from.trim(), to.trim());
+    (!from.is_empty() && !to.is_empty()).then_some((from, to))
+}
+
+impl CompanionConfig {
+    /// Returns the [`REQUIRED_ANIMATIONS`] that are missing or have no frames.
+    pub fn missing_animations(&self) -> impl Iterator<Item = &'static str> {
+        REQUIRED_ANIMATIONS.iter().copied().filter(|name| {
+            self.animations
+                .get(*name)
+                .is_none_or(|animation| animation.frames.is_empty())
+        })
+    }
+}
+
+/// Name of the application's directory inside the XDG base directories.
+const APP_DIR: &str = "desktop-companion";
+
+/// Animations every companion must provide.
+pub const REQUIRED_ANIMATIONS: &[&str] = &["idle", "walk"];
+
+/// Name of the main configuration file.
+const CONFIG_FILE: &str = "config.toml";
+
+/// Returns the directories searched for `config.toml`, in order of preference.
+///
+/// 1. `$XDG_CONFIG_HOME/desktop-companion`, or `~/.config/desktop-companion` if unset
+/// 2. `/usr/share/desktop-companion` for system-wide installs
+/// 3. `./config` so the app still runs straight from a source checkout
+fn config_search_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    if let Some(config_home) = config_home {
+        dirs.push(config_home.join(APP_DIR));
+    }
+
+    dirs.push(Path::new("/usr/share").join(APP_DIR));
+    dirs.push(PathBuf::from("./config"));
+    dirs
+}
+
+/// Locates `config.toml`.
+///
+/// An explicitly given path is used as is, or joined with `config.toml` if it is a directory.
+/// Otherwise the directories from [`config_search_dirs`] are searched in order.
+///
+/// # Arguments
+/// * `explicit` - Path passed with `--config`, if any.
+///
+/// # Returns
+/// * `Ok(PathBuf)` with the path of the configuration file.
+/// * `Err(CompanionError)` if no configuration file was found.
+pub fn find_config(explicit: Option<&Path>) -> Result<PathBuf, CompanionError> {
+    if let Some(path) = explicit {
+        let path = if path.is_dir() {
+            path.join(CONFIG_FILE)
+        } else {
+            path.to_path_buf()
+        };
+        return if path.is_file() {
+            Ok(path)
+        } else {
+            Err(CompanionError::MissingFile {
+                path,
+                source: io::ErrorKind::NotFound.into(),
+            })
+        };
+    }
+
+    let searched = config_search_dirs();
+    searched
+        .iter()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|path| path.is_file())
+        .ok_or(CompanionError::ConfigNotFound { searched })
+}
+
+/// Reads and deserializes a TOML configuration file.
+fn load_toml<T: DeserializeOwned>(path: &Path) -> Result<T, CompanionError> {
+    Config::builder()
+        .add_source(config::File::from(path))
         .build()
-        .unwrap();
+        .and_then(|settings| settings.try_deserialize::<T>())
+        .map_err(|source| CompanionError::BadConfig {
+            path: path.to_path_buf(),
+            source,
+        })
+}
 
-    settings.try_deserialize::<AppConfig>()
+/// Loads the main application configuration.
+///
+/// # Arguments
+/// * `path` - Path to `config.toml`.
+///
+/// # Returns
+/// * `Ok(AppConfig)` if the file exists and deserializes successfully.
+/// * `Err(CompanionError)` if the file cannot be read or deserialized.
+pub fn load_config(path: &Path) -> Result<AppConfig, CompanionError> {
+    load_toml(path)
 }
 
-/// Loads a companion's animation configuration from a specified file path.
+/// Loads a companion's animation and behavior configuration from a specified file path.
 ///
 /// # Arguments
 /// * `path` - Path to the TOML file containing companion animations.
 ///
 /// # Returns
 /// * `Ok(CompanionConfig)` if the file exists and deserializes successfully.
-/// * `Err(ConfigError)` if the file cannot be read or deserialized.
-pub fn load_companion_config(path: &str) -> Result<CompanionConfig, ConfigError> {
-    let settings = Config::builder()
-        .add_source(config::File::with_name(path))
-        .build()
-        .unwrap();
+/// * `Err(CompanionError)` if the file cannot be read or deserialized, if its transition
+///   matrix is invalid, or if one of the [`REQUIRED_ANIMATIONS`] is missing.
+pub fn load_companion_config(path: &Path) -> Result<CompanionConfig, CompanionError> {
+    let config = read_companion_config(path)?;
+
+    if let Some(animation) = config.missing_animations().next() {
+        return Err(CompanionError::MissingAnimation {
+            animation: animation.to_string(),
+            config: path.to_path_buf(),
+        });
+    }
+
+    Ok(config)
+}
+
+/// Reads a companion's configuration without checking that the required animations are present.
+///
+/// Animations imported from Aseprite exports are read here as well, relative to the directory of
+/// `path`.
+pub fn read_companion_config(path: &Path) -> Result<CompanionConfig, CompanionError> {
+    parse_companion_config(path, read_companion_table(path)?)
+}
+
+/// Reads `companion.toml` as a table of raw values, so parts of it can be checked on their own.
+///
+/// Used by the `validate` command to report every problem of a companion at once, which
+/// deserializing the whole file would stop at the first one.
+pub fn read_companion_table(path: &Path) -> Result<Map<String, Value>, CompanionError> {
+    load_toml(path)
+}
+
+/// Deserializes a companion's configuration from the `table` read from `path` by
+/// [`read_companion_table`], like [`read_companion_config`] does.
+pub fn parse_companion_config(
+    path: &Path,
+    table: Map<String, Value>,
+) -> Result<CompanionConfig, CompanionError> {
+    let mut config: CompanionConfig =
+        Value::new(None, table)
+            .try_deserialize()
+            .map_err(|source| CompanionError::BadConfig {
+                path: path.to_path_buf(),
+                source,
+            })?;
+    let root = path.parent().unwrap_or(Path::new("."));
+
+    // exports are usually shared by several animations
+    let mut exports: HashMap<String, AsepriteExport> = HashMap::new();
+    for animation in config.animations.values_mut() {
+        let Some(source) = animation.aseprite.take() else {
+            continue;
+        };
+        let json_path = root.join(&source.aseprite);
+        if !exports.contains_key(&source.aseprite) {
+            exports.insert(source.aseprite.clone(), AsepriteExport::load(&json_path)?);
+        }
+        *animation = exports[&source.aseprite].animation(&json_path, &source)?;
+    }
+
+    Ok(config)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// Writes a pack with placeholder sprites to a fresh temporary directory.
+    ///
+    /// The sprites aren't images, which only companions running without graphics accept.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the companion, unique per test.
+    /// * `config` - Contents of `companion.toml`, every `*.png` it mentions is created.
+    pub(crate) fn write_pack(name: &str, config: &str) -> CompanionPack {
+        let root = env::temp_dir().join(format!("desktop-companion-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("companion.toml"), config).unwrap();
+        for sprite in config.split('"').filter(|part| part.ends_with(".png")) {
+            fs::write(root.join(sprite), "placeholder").unwrap();
+        }
+
+        CompanionPack::new(&root.join("config.toml"), companion(name, "."))
+    }
+
+    fn companion(name: &str, path: &str) -> Companion {
+        Companion {
+            name: name.to_string(),
+            path: path.to_string(),
+            width: 96.0,
+            height: 96.0,
+            walkspeed: 100.0,
+            jump_height: default_jump_height(),
+            jump_distance: default_jump_distance(),
+            physics: PhysicsConfig::default(),
+        }
+    }
+
+    #[test]
+    fn pack_is_located_by_path_not_name() {
+        let pack = CompanionPack::new(
+            Path::new("/cfg/config.toml"),
+            companion("Niko the Cat", "niko"),
+        );
+
+        assert_eq!(pack.root(), Path::new("/cfg/niko"));
+        assert_eq!(
+            pack.config_path(),
+            PathBuf::from("/cfg/niko/companion.toml")
+        );
+        assert_eq!(
+            pack.asset_path("idle1.png"),
+            PathBuf::from("/cfg/niko/idle1.png")
+        );
+    }
+
+    #[test]
+    fn absolute_pack_path_ignores_config_directory() {
+        let pack = CompanionPack::new(
+            Path::new("/cfg/config.toml"),
+            companion("niko", "/opt/companions/niko"),
+        );
+
+        assert_eq!(
+            pack.config_path(),
+            PathBuf::from("/opt/companions/niko/companion.toml")
+        );
+    }
+
+    #[test]
+    fn absolute_asset_path_is_unchanged() {
+        let pack = CompanionPack::new(Path::new("/cfg/config.toml"), companion("niko", "niko"));
+
+        assert_eq!(
+            pack.asset_path("/usr/share/sprites/idle1.png"),
+            PathBuf::from("/usr/share/sprites/idle1.png")
+        );
+    }
+
+    #[test]
+    fn sequence_steps_are_parsed() {
+        let pack = write_pack(
+            "sequence-steps",
+            r#"
+[animations]
+idle = [{ path = "idle1.png" }]
+walk = [{ path = "walk1.png" }]
+
+[sequences]
+walk_right = [
+    { walk = "right_edge" },
+    { walk = -100, animation = "sneak", easing = "ease_in_quad" },
+    { play = "sit", seconds = 3.0 },
+    { parallel = [{ walk = 50 }, { play = "wave" }] },
+]
+"#,
+        );
+        let config = pack.load_config().unwrap();
+        let steps = &config.sequences[&Behavior::WalkRight];
+
+        assert!(matches!(
+            &steps[0],
+            StepConfig::Walk {
+                walk: WalkTarget::Edge(Edge::RightEdge),
+                animation: None,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &steps[1],
+            StepConfig::Walk { walk: WalkTarget::By(-100.0), animation: Some(name), easing: Easing::EaseInQuad }
+                if name == "sneak"
+        ));
+        assert!(matches!(
+            &steps[2],
+            StepConfig::Play { play, seconds: Some(3.0) } if play == "sit"
+        ));
+        assert!(matches!(&steps[3], StepConfig::Parallel { parallel } if parallel.len() == 2));
+        assert_eq!(steps[3].animations(), ["walk", "wave"]);
+    }
+
+    #[test]
+    fn unknown_walk_target_is_rejected() {
+        let pack = write_pack(
+            "sequence-bad-target",
+            r#"
+[animations]
+idle = [{ path = "idle1.png" }]
+
+[sequences]
+idle = [{ walk = "top_edge" }]
+"#,
+        );
 
-    settings.try_deserialize::<CompanionConfig>()
+        assert!(matches!(
+            pack.load_config(),
+            Err(CompanionError::BadConfig { .. })
+        ));
+    }
 }
@@ -1,214 +1,621 @@
-use std::{collections::HashMap, env::current_dir, fs, time::Instant};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use ggez::{
-    event::{EventHandler, MouseButton}, graphics::{self, Color, Image}, winit::{
-        self,
-        dpi::{LogicalPosition, PhysicalPosition, PhysicalSize}, window,
-    }, Context, GameError, GameResult
+    Context, GameError, GameResult,
+    event::{EventHandler, MouseButton},
+    graphics::{self, Color, Image, Rect},
 };
 
-use log::debug;
+use image::{
+    AnimationDecoder, ImageError, ImageFormat,
+    codecs::{gif::GifDecoder, png::PngDecoder},
+};
+use log::{debug, warn};
 use rand::Rng;
 
 use crate::{
     animation::{
         AnimationTrait, CompanionAnimations,
-        idle::IdleAnimation,
+        drag::DragAnimation,
+        easing::Easing,
+        idle::{Fidget, IdleAnimation},
         movement::{Direction, MoveAnimation},
+        physics::PhysicsAnimation,
+        pose::PoseAnimation,
+        sequence::{LazyAnimation, SequenceAnimation},
+        timeline::{Frame, Timeline},
+    },
+    behavior::{Behavior, BehaviorManager, Environment, SharedRng, seeded_rng},
+    clock::{SharedClock, SystemClock},
+    companion::{
+        Companion, CompanionConfig, CompanionPack, Edge, StepConfig, WalkTarget,
+        split_transition_key,
     },
-    behavior::{Behavior, BehaviorManager},
-    companion::{Companion, CompanionConfig},
+    errors::CompanionError,
+    physics::{Body, Bounds, VelocityTracker},
+    window::{GgezWindow, WindowBackend},
 };
 
+/// Release speed in pixels per second above which the companion gets dizzy after landing.
+const DIZZY_SPEED: f32 = 800.0;
+
+/// How long the companion stays dizzy in seconds.
+const DIZZY_DURATION: f32 = 2.0;
+
+/// Upper limit of the release speed so a flick of the mouse doesn't send the companion flying
+/// off to another monitor.
+const MAX_THROW_SPEED: f32 = 4000.0;
+
 pub struct CompanionApp {
     pub companion_data: Companion,
     pub animations: CompanionAnimations,
     pub behavior: BehaviorManager,
-    pub monitor_size: PhysicalSize<u32>,
+    pub monitor_size: (f32, f32),
     pub dragging: bool,
+    pub drag_animation: Option<DragAnimation>,
     pub drag_coords: (f32, f32),
     pub window_start: (f32, f32),
-    pub frames: HashMap<String, Vec<Image>>,
+    pub drag_velocity: VelocityTracker,
+    pub dizzy_pending: bool,
+    pub frames: HashMap<String, Timeline>,
+    pub easing: HashMap<Behavior, Easing>,
+    pub sequences: HashMap<Behavior, Vec<StepConfig>>,
+    pub rng: SharedRng,
+    pub clock: SharedClock,
     pub initialized: bool,
 }
 
-fn read_image(ctx: &mut Context, path: &str) -> Result<Image, GameError> {
-    let sprite_bytes = fs::read(path).unwrap_or_else(|_| panic!("Failed to read file {}", &path));
-    Image::from_bytes(ctx, &sprite_bytes)
+/// Image of a sprite file with its embedded delay, if the file is animated and has one. The image
+/// is `None` when loading without a graphics context.
+type DecodedFrame = (Option<Image>, Option<Duration>);
+
+/// Reads a sprite file, decoding every frame of animated GIFs and APNGs.
+///
+/// Without a graphics context only the delays of animated files are decoded, still images
+/// aren't decoded at all.
+fn read_frames(
+    mut ctx: Option<&mut Context>,
+    path: &Path,
+) -> Result<Vec<DecodedFrame>, CompanionError> {
+    let bytes = fs::read(path).map_err(|source| CompanionError::MissingFile {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let undecodable = |e: ImageError| CompanionError::UndecodableImage {
+        path: path.to_path_buf(),
+        source: GameError::ResourceLoadError(e.to_string()),
+    };
+
+    let animation = match image::guess_format(&bytes) {
+        Ok(ImageFormat::Gif) => Some(
+            GifDecoder::new(Cursor::new(&bytes))
+                .map_err(undecodable)?
+                .into_frames(),
+        ),
+        Ok(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(Cursor::new(&bytes)).map_err(undecodable)?;
+            decoder.is_apng().then(|| decoder.apng().into_frames())
+        }
+        _ => None,
+    };
+
+    let Some(animation) = animation else {
+        let image = ctx
+            .map(|ctx| Image::from_bytes(ctx, &bytes))
+            .transpose()
+            .map_err(|source| CompanionError::UndecodableImage {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        return Ok(vec![(image, None)]);
+    };
+
+    animation
+        .map(|frame| {
+            let frame = frame.map_err(undecodable)?;
+            let (numerator, denominator) = frame.delay().numer_denom_ms();
+            // a zero delay means "as fast as possible"; like browsers, fall back to the
+            // sprite's `duration_ms` instead
+            let delay = (numerator > 0)
+                .then(|| Duration::from_secs_f64(numerator as f64 / denominator as f64 / 1000.0));
+            let image = ctx.as_deref_mut().map(|ctx| {
+                let buffer = frame.into_buffer();
+                Image::from_pixels(
+                    ctx,
+                    buffer.as_raw(),
+                    graphics::ImageFormat::Rgba8UnormSrgb,
+                    buffer.width(),
+                    buffer.height(),
+                )
+            });
+            Ok((image, delay))
+        })
+        .collect()
+}
+
+/// Creates a walk from `start` to `target_x` at `walkspeed` pixels per second.
+///
+/// # Arguments
+/// * `start` - Position of the window when the walk starts.
+/// * `target_x` - Horizontal position to walk to.
+/// * `walkspeed` - Speed in pixels per second, walks take half a second if it isn't positive.
+/// * `timeline` - Frames shown while walking.
+/// * `direction` - Direction the companion faces.
+/// * `easing` - Curve applied to the movement.
+/// * `clock` - Source of the current time.
+fn walk_animation(
+    start: (f32, f32),
+    target_x: f32,
+    walkspeed: f32,
+    timeline: Timeline,
+    direction: Direction,
+    easing: Easing,
+    clock: SharedClock,
+) -> MoveAnimation {
+    let duration = if walkspeed > 0.0 {
+        // here we use .abs() to get modulus of distance because it can be
+        // negative if we're walking left
+        ((target_x - start.0).abs() / walkspeed).max(0.1)
+    } else {
+        0.5
+    };
+
+    MoveAnimation {
+        start_pos: start,
+        end: (target_x, start.1),
+        duration,
+        start_time: clock.now(),
+        finished: false,
+        current_pos: start,
+        timeline,
+        direction,
+        easing,
+        clock,
+    }
 }
 
 impl CompanionApp {
     pub fn new(
         ctx: &mut Context,
-        companion_data: Companion,
+        pack: &CompanionPack,
+        companion_config: CompanionConfig,
+        seed: u64,
+    ) -> Result<CompanionApp, CompanionError> {
+        let monitor_size = GgezWindow::new(ctx).monitor_size();
+        Self::load(
+            Some(ctx),
+            pack,
+            companion_config,
+            seed,
+            monitor_size,
+            Arc::new(SystemClock),
+        )
+    }
+
+    /// Creates a companion without a window or graphics, which only knows how long its frames
+    /// last. Used for [`simulate`](crate::simulate)-ing it.
+    ///
+    /// # Arguments
+    /// * `pack` - Pack of the companion.
+    /// * `companion_config` - Configuration loaded from the pack.
+    /// * `seed` - Seed for the companion's RNG.
+    /// * `monitor_size` - Size of the simulated monitor.
+    /// * `clock` - Source of the simulated time.
+    pub fn headless(
+        pack: &CompanionPack,
+        companion_config: CompanionConfig,
+        seed: u64,
+        monitor_size: (f32, f32),
+        clock: SharedClock,
+    ) -> Result<CompanionApp, CompanionError> {
+        Self::load(None, pack, companion_config, seed, monitor_size, clock)
+    }
+
+    /// Loads the frames of the companion, with images if there is a graphics context.
+    fn load(
+        mut ctx: Option<&mut Context>,
+        pack: &CompanionPack,
         companion_config: CompanionConfig,
-    ) -> CompanionApp {
+        seed: u64,
+        monitor_size: (f32, f32),
+        clock: SharedClock,
+    ) -> Result<CompanionApp, CompanionError> {
+        let companion_data = pack.companion.clone();
+        let rng = seeded_rng(seed);
         let mut frames_map = HashMap::new();
-        let monitor_size = ctx
-            .gfx
-            .window()
-            .current_monitor()
-            .expect("Failed to get current monitor")
-            .size();
-
-        for (behavior, frames) in &companion_config.animations {
-            let images: Vec<Image> = frames
-                .iter()
-                .map(|f| {
-                    let path = current_dir()
-                        .unwrap()
-                        .join("config")
-                        .join(&companion_data.name)
-                        .join(&f.path);
 
+        // sprite sheets are shared by many frames, so every file is only loaded once
+        let mut images: HashMap<PathBuf, Vec<DecodedFrame>> = HashMap::new();
+        for (behavior, animation) in &companion_config.animations {
+            let mut frames = Vec::new();
+            for f in &animation.frames {
+                let path = pack.asset_path(&f.path);
+                if !images.contains_key(&path) {
                     debug!("{:?}", path);
-                    read_image(ctx, path.to_str().unwrap()).unwrap()
-                })
-                .collect();
-            frames_map.insert(behavior.to_string(), images);
+                    let decoded = read_frames(ctx.as_deref_mut(), &path)?;
+                    images.insert(path.clone(), decoded);
+                }
+
+                for (image, delay) in &images[&path] {
+                    let src = match (f.rect, image) {
+                        (Some(rect), Some(image)) => {
+                            let (width, height) = (image.width() as f32, image.height() as f32);
+                            Rect::new(
+                                rect.x as f32 / width,
+                                rect.y as f32 / height,
+                                rect.w as f32 / width,
+                                rect.h as f32 / height,
+                            )
+                        }
+                        _ => Rect::one(),
+                    };
+
+                    frames.push(Frame {
+                        image: image.clone(),
+                        src,
+                        duration: delay.unwrap_or(Duration::from_millis(f.duration_ms)),
+                    });
+                }
+            }
+            frames_map.insert(
+                behavior.to_string(),
+                Timeline::new(frames, animation.loop_mode),
+            );
+        }
+        let idle_timeline = frames_map
+            .get("idle")
+            .filter(|timeline| !timeline.is_empty())
+            .cloned()
+            .ok_or_else(|| CompanionError::MissingAnimation {
+                animation: "idle".to_string(),
+                config: pack.config_path(),
+            })?;
+        let behavior =
+            BehaviorManager::new(companion_config.transitions, rng.clone(), clock.clone());
+        let drag_animation = frames_map
+            .get("drag")
+            .filter(|timeline| !timeline.is_empty())
+            .map(|timeline| DragAnimation {
+                timeline: timeline.clone(),
+                speed: 0.0,
+            });
+        let mut fidgets = Vec::new();
+        for (name, fidget) in &companion_config.fidgets {
+            match frames_map.get(name).filter(|timeline| !timeline.is_empty()) {
+                Some(timeline) => fidgets.push(Fidget {
+                    timeline: timeline.clone(),
+                    every: Duration::from_secs_f32(fidget.every.max(0.0)),
+                    jitter: Duration::from_secs_f32(fidget.jitter.max(0.0)),
+                }),
+                None => warn!("Ignoring fidget `{name}`, there is no such animation"),
+            }
         }
-        let mut animations = CompanionAnimations::new();
+        let mut transitions = HashMap::new();
+        for (key, name) in &companion_config.animation_transitions {
+            let frames = frames_map.get(name).filter(|timeline| !timeline.is_empty());
+            match (split_transition_key(key), frames) {
+                (Some((from, to)), Some(timeline)) => {
+                    transitions.insert((from.to_string(), to.to_string()), timeline.clone());
+                }
+                (None, _) => warn!("Ignoring transition `{key}`, it should look like `from->to`"),
+                (_, None) => warn!("Ignoring transition `{key}`, there is no animation `{name}`"),
+            }
+        }
+        let mut animations = CompanionAnimations::new(
+            transitions,
+            Duration::from_millis(companion_config.crossfade_ms),
+            clock.clone(),
+        );
         animations.push(
-            Box::new(IdleAnimation {
-                sprite_frames: frames_map["idle"].clone(),
-            }),
+            Box::new(IdleAnimation::new(
+                idle_timeline,
+                fidgets,
+                rng.clone(),
+                clock.clone(),
+            )),
             "idle".into(),
         );
-        CompanionApp {
+        Ok(CompanionApp {
             companion_data,
             animations,
-            behavior: BehaviorManager::new(ctx.gfx.window() as *const winit::window::Window),
+            behavior,
             monitor_size,
             dragging: false,
+            drag_animation,
             drag_coords: (0.0, 0.0),
             window_start: (0.0, 0.0),
+            drag_velocity: VelocityTracker::new(clock.clone()),
+            dizzy_pending: false,
             frames: frames_map,
+            easing: companion_config.easing,
+            sequences: companion_config.sequences,
+            rng,
+            clock,
             initialized: false,
+        })
+    }
+
+    /// Advances the companion by one frame: picks the next behavior once the previous one has
+    /// finished and updates the animations.
+    ///
+    /// # Arguments
+    /// * `window` - Window of the companion, real or simulated.
+    ///
+    /// # Returns
+    /// The behavior started during this frame, if any.
+    pub fn step(&mut self, window: &mut dyn WindowBackend) -> GameResult<Option<Behavior>> {
+        let mut started = None;
+        if !self.initialized {
+            self.initialize(window)?;
+        } else if !self.animations.is_busy() && self.dizzy_pending {
+            self.dizzy_pending = false;
+            self.start_dizzy();
+        } else if !self.animations.is_busy()
+            && let Some(behavior) = self.behavior.update(&self.environment(window))
+        {
+            self.start_behavior(window, behavior)?;
+            started = Some(behavior);
         }
+
+        if !self.dragging {
+            self.animations.update(window);
+        } else if let Some(drag_animation) = &mut self.drag_animation {
+            drag_animation.speed = self.drag_velocity.velocity().0;
+        }
+        Ok(started)
     }
 
-    fn initialize(&mut self, ctx: &mut Context) -> GameResult {
-        let window = ctx.gfx.window();
-        if let Some(true) = window.is_visible() {
-            self.move_window(ctx, (self.monitor_size.width as i32 / 2, 0));
-
-            let fall_animation = MoveAnimation {
-                start_pos: (self.monitor_size.width as f32 / 2.0, -50.0),
-                end: (
-                    self.monitor_size.width as f32 / 2.0,
-                    self.monitor_size.height as f32 - self.companion_data.height,
-                ),
-                duration: 0.6,
-                start_time: Instant::now(),
-                finished: false,
-                current_pos: (0.0, 0.0),
-                sprite_frames: vec![self.frames["idle"][0].clone()],
-                direction: Direction::Vertical,
-            };
+    /// Takes a snapshot of the companion's surroundings for the behavior manager.
+    fn environment(&self, window: &dyn WindowBackend) -> Environment {
+        Environment {
+            position: window.position().unwrap_or_default(),
+            size: (self.companion_data.width, self.companion_data.height),
+            screen: self.monitor_size,
+            cursor: window.cursor_position(),
+        }
+    }
+
+    fn initialize(&mut self, window: &mut dyn WindowBackend) -> GameResult {
+        if window.is_visible() {
+            window.set_position((self.monitor_size.0 / 2.0, 0.0));
 
-            self.start_animation(fall_animation, "fall", ctx);
+            let body = Body::new((self.monitor_size.0 / 2.0, -50.0), (0.0, 0.0));
+            self.start_physics(body, "fall");
             self.initialized = true;
         }
         Ok(())
     }
 
-    fn start_behavior(&mut self, ctx: &mut Context, behavior: Behavior) -> GameResult {
-        let cur_pos = ctx
-            .gfx
-            .window_position()
-            .expect("Failed to get window position");
+    fn start_behavior(&mut self, window: &dyn WindowBackend, behavior: Behavior) -> GameResult {
+        let cur_pos = window.position().expect("Failed to get window position");
 
-        let mut rng = rand::rng();
+        if let Some(steps) = self.sequences.get(&behavior) {
+            // under a key of its own, so a sequence for `idle` doesn't replace the idle animation
+            let sequence = self.build_sequence(steps);
+            self.start_animation(sequence, &format!("sequence:{}", behavior.name()));
+            return Ok(());
+        }
 
         match behavior {
             Behavior::Idle => {
-                self.animations.push(
-                    Box::new(IdleAnimation {
-                        sprite_frames: self.frames["idle"].clone(),
-                    }),
-                    "idle".into(),
-                );
+                self.animations.start("idle");
             }
             Behavior::WalkLeft | Behavior::WalkRight => {
-                let cur_x = cur_pos.x as f32;
+                let cur_x = cur_pos.0;
 
                 let max_step = 200.0;
 
                 let target_x = match behavior {
                     Behavior::WalkLeft => {
-                        (cur_x - rng.random_range(50.0..max_step)) // step left
+                        (cur_x - self.rng.lock().unwrap().random_range(50.0..max_step)) // step left
                             .max(0.0)
                     }
                     Behavior::WalkRight => {
-                        (cur_x + rng.random_range(50.0..max_step))
-                            .min(self.monitor_size.width as f32 - self.companion_data.width) // step
+                        (cur_x + self.rng.lock().unwrap().random_range(50.0..max_step))
+                            .min(self.bounds().max_x) // step
                         // riiiiight
                     }
                     _ => cur_x,
                 };
-                let duration = if self.companion_data.walkspeed > 0.0 {
-                    // here we use .abs() to get modulus of distance because it can be
-                    // negative if we're walking left
-                    ((target_x - cur_x).abs() / self.companion_data.walkspeed).max(0.1)
-                } else {
-                    0.5
-                };
-
                 debug!(
-                    "max_step {max_step} target_x {target_x} cur_x {cur_x} step {} duration {}",
-                    cur_x - target_x,
-                    duration
+                    "max_step {max_step} target_x {target_x} cur_x {cur_x} step {}",
+                    cur_x - target_x
                 );
 
-                let walk_animation = MoveAnimation {
-                    start_pos: (cur_pos.x as f32, cur_pos.y as f32),
-                    end: (target_x, cur_pos.y as f32),
-                    duration,
-                    start_time: Instant::now(),
-                    finished: false,
-                    current_pos: (cur_pos.x as f32, cur_pos.y as f32),
-                    sprite_frames: self.frames["walk"].clone(),
-                    direction: if behavior == Behavior::WalkLeft {
+                let walk_animation = walk_animation(
+                    (cur_x, cur_pos.1),
+                    target_x,
+                    self.companion_data.walkspeed,
+                    self.frames["walk"].clone(),
+                    if behavior == Behavior::WalkLeft {
                         Direction::Left
                     } else {
                         Direction::Right
                     },
+                    self.easing.get(&behavior).copied().unwrap_or_default(),
+                    self.clock.clone(),
+                );
+
+                self.start_animation(walk_animation, "walk");
+            }
+            Behavior::Jump => {
+                let cur_x = cur_pos.0;
+                let max_x = self.bounds().max_x;
+                let gravity = self.companion_data.physics.gravity;
+
+                let distance = if self.rng.lock().unwrap().random_bool(0.5) {
+                    -self.companion_data.jump_distance
+                } else {
+                    self.companion_data.jump_distance
                 };
+                let target_x = (cur_x + distance).clamp(0.0, max_x);
+
+                // don't let the apex leave the top of the monitor
+                let height = self.companion_data.jump_height.min(cur_pos.1).max(0.0);
 
-                self.start_animation(walk_animation, "walk", ctx);
+                // launch speed needed to reach `height`, and the time to go up and back down
+                let vel_y = -(2.0 * gravity * height).sqrt();
+                let flight_time = (2.0 * -vel_y / gravity).max(0.1);
+                let vel_x = (target_x - cur_x) / flight_time;
+
+                debug!(
+                    "jump target_x {target_x} cur_x {cur_x} height {height} vel ({vel_x}, {vel_y})"
+                );
+
+                let body = Body::new((cur_x, cur_pos.1), (vel_x, vel_y));
+                self.start_physics(body, "jump");
             }
-            Behavior::Fall | Behavior::Jump => {
-                // TODO: implement
+            Behavior::Fall => {
+                // reappear above the top of the screen and drop down from there
+                let body = Body::new((cur_pos.0, -self.companion_data.height), (0.0, 0.0));
+                self.start_physics(body, "fall");
             }
         }
 
         Ok(())
     }
 
-    fn move_window(&mut self, ctx: &mut Context, pos: (i32, i32)) {
-        let window = ctx.gfx.window();
-        window.set_outer_position(LogicalPosition::new(pos.0, pos.1));
+    /// Builds the animation of a scripted sequence from `[sequences]`.
+    fn build_sequence(&self, steps: &[StepConfig]) -> SequenceAnimation {
+        let mut sequence = SequenceAnimation::new();
+        for step in steps {
+            let mut animations = self.build_step(step).into_iter();
+            if let Some(first) = animations.next() {
+                sequence = sequence.then(first);
+            }
+            for animation in animations {
+                sequence = sequence.with(animation);
+            }
+        }
+        sequence
+    }
+
+    /// Builds the animations of a single step of a sequence, several for parallel steps.
+    fn build_step(&self, step: &StepConfig) -> Vec<Box<dyn AnimationTrait>> {
+        match step {
+            StepConfig::Play { play, seconds } => {
+                let timeline = self.frames_or_idle(play);
+                let duration = seconds.unwrap_or(timeline.cycle_duration().as_secs_f32());
+                vec![Box::new(PoseAnimation {
+                    timeline,
+                    duration,
+                    start_time: self.clock.now(),
+                    clock: self.clock.clone(),
+                })]
+            }
+            StepConfig::Walk {
+                walk,
+                animation,
+                easing,
+            } => {
+                let timeline = self.frames_or_idle(animation.as_deref().unwrap_or("walk"));
+                let (walk, easing) = (*walk, *easing);
+                let walkspeed = self.companion_data.walkspeed;
+                let max_x = self.bounds().max_x;
+                let clock = self.clock.clone();
+
+                // the walk starts wherever the previous steps left the companion
+                vec![Box::new(LazyAnimation::new(move |window| {
+                    let start = window.position().expect("Failed to get window position");
+                    let target_x = match walk {
+                        WalkTarget::Edge(Edge::LeftEdge) => 0.0,
+                        WalkTarget::Edge(Edge::RightEdge) => max_x,
+                        WalkTarget::By(distance) => start.0 + distance,
+                    }
+                    .clamp(0.0, max_x);
+                    let direction = if target_x < start.0 {
+                        Direction::Left
+                    } else {
+                        Direction::Right
+                    };
+
+                    Box::new(walk_animation(
+                        start,
+                        target_x,
+                        walkspeed,
+                        timeline.clone(),
+                        direction,
+                        easing,
+                        clock.clone(),
+                    ))
+                }))]
+            }
+            StepConfig::Parallel { parallel } => parallel
+                .iter()
+                .flat_map(|step| self.build_step(step))
+                .collect(),
+        }
+    }
+
+    /// Returns the frames of the animation `name`, or the idle frames if the companion doesn't
+    /// provide it.
+    fn frames_or_idle(&self, name: &str) -> Timeline {
+        self.frames
+            .get(name)
+            .filter(|timeline| !timeline.is_empty())
+            .unwrap_or(&self.frames["idle"])
+            .clone()
+    }
+
+    /// Area of the monitor the companion's window can move in.
+    ///
+    /// On monitors narrower than the companion it is pinned to the left edge.
+    fn bounds(&self) -> Bounds {
+        Bounds {
+            min_x: 0.0,
+            max_x: (self.monitor_size.0 - self.companion_data.width).max(0.0),
+            ground_y: self.monitor_size.1 - self.companion_data.height,
+        }
+    }
+
+    /// Starts simulating `body` with the companion's physics, showing the frames of the
+    /// animation `name` (or idle frames) until it comes to rest.
+    fn start_physics(&mut self, body: Body, name: &str) {
+        let animation = PhysicsAnimation::new(
+            body,
+            self.companion_data.physics,
+            self.bounds(),
+            self.frames_or_idle(name),
+            self.clock.clone(),
+        );
+        self.start_animation(animation, name);
     }
 
-    fn start_animation(&mut self, animation: MoveAnimation, name: &str, ctx: &mut Context) {
+    /// Shows the `dizzy` animation for [`DIZZY_DURATION`] if the companion provides one.
+    fn start_dizzy(&mut self) {
+        if let Some(timeline) = self
+            .frames
+            .get("dizzy")
+            .filter(|timeline| !timeline.is_empty())
+        {
+            let dizzy_animation = PoseAnimation {
+                timeline: timeline.clone(),
+                duration: DIZZY_DURATION,
+                start_time: self.clock.now(),
+                clock: self.clock.clone(),
+            };
+            self.start_animation(dizzy_animation, "dizzy");
+        }
+    }
+
+    fn start_animation(&mut self, animation: impl AnimationTrait + 'static, name: &str) {
         let mut anim = Box::new(animation);
         anim.start();
         self.animations.push(anim, name.to_string());
-        self.animations.start(name, ctx);
+        self.animations.start(name);
     }
 }
 
 impl EventHandler for CompanionApp {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        if !self.initialized {
-            self.initialize(ctx)?;
-        } else if self.animations.active.is_none() {
-            if let Some(behavior) = self.behavior.update() {
-                self.start_behavior(ctx, behavior)?;
-            }
-        }
-
-        if !self.dragging { self.animations.update(ctx); }
+        self.step(&mut GgezWindow::new(ctx))?;
         Ok(())
     }
 
@@ -220,14 +627,16 @@ impl EventHandler for CompanionApp {
         y: f32,
     ) -> Result<(), GameError> {
         if button == MouseButton::Left {
-            self.animations.start("idle", ctx);
-            if let Ok(window_pos) = ctx.gfx.window_position() {
-                let mouse_screen = PhysicalPosition::new(
-                    x as f64 + window_pos.x as f64,
-                    y as f64 + window_pos.y as f64,
-                );
-                self.drag_coords = (mouse_screen.x as f32, mouse_screen.y as f32);
-                self.window_start = (window_pos.x as f32, window_pos.y as f32);
+            self.animations.start("idle");
+            if let Some(window_pos) = GgezWindow::new(ctx).position() {
+                self.drag_coords = (x + window_pos.0, y + window_pos.1);
+                self.window_start = window_pos;
+                self.drag_velocity.clear();
+                self.drag_velocity.record(self.drag_coords);
+                self.dizzy_pending = false;
+                if let Some(drag_animation) = &mut self.drag_animation {
+                    drag_animation.start();
+                }
                 self.dragging = true;
             }
         }
@@ -243,20 +652,15 @@ impl EventHandler for CompanionApp {
         _dy: f32,
     ) -> Result<(), GameError> {
         if self.dragging {
-            let window = ctx.gfx.window();
-            if let Ok(window_pos) = window.outer_position() {
-                let mouse_screen = PhysicalPosition::new(
-                    x as f64 + window_pos.x as f64,
-                    y as f64 + window_pos.y as f64,
-                );
+            let mut window = GgezWindow::new(ctx);
+            if let Some(window_pos) = window.position() {
+                let mouse_screen = (x + window_pos.0, y + window_pos.1);
 
-                let dx = mouse_screen.x - self.drag_coords.0 as f64;
-                let dy = mouse_screen.y - self.drag_coords.1 as f64;
+                let dx = mouse_screen.0 - self.drag_coords.0;
+                let dy = mouse_screen.1 - self.drag_coords.1;
 
-                let new_x = self.window_start.0 as f64 + dx;
-                let new_y = self.window_start.1 as f64 + dy;
-
-                window.set_outer_position(PhysicalPosition::new(new_x, new_y));
+                self.drag_velocity.record(mouse_screen);
+                window.set_position((self.window_start.0 + dx, self.window_start.1 + dy));
             }
         }
         Ok(())
@@ -270,30 +674,138 @@ impl EventHandler for CompanionApp {
         _y: f32,
     ) -> Result<(), GameError> {
         self.dragging = false;
-        let window = ctx.gfx.window();
-        if window.outer_position().expect("Failed to get window outer_position").y != 0 {
-            let fall_animation = MoveAnimation {
-                start_pos: (window.outer_position().unwrap().x as f32, window.outer_position().unwrap().y as f32),
-                end: (
-                    window.outer_position().unwrap().x as f32,
-                    self.monitor_size.height as f32 - self.companion_data.height,
-                ),
-                duration: 0.6,
-                start_time: Instant::now(),
-                finished: false,
-                current_pos: (0.0, 0.0),
-                sprite_frames: vec![self.frames["idle"][0].clone()],
-                direction: Direction::Vertical,
+        let pos = GgezWindow::new(ctx)
+            .position()
+            .expect("Failed to get window outer_position");
+        if pos.1 != 0.0 {
+            let (vel_x, vel_y) = self.drag_velocity.velocity();
+            let speed = vel_x.hypot(vel_y);
+            let scale = if speed > MAX_THROW_SPEED {
+                MAX_THROW_SPEED / speed
+            } else {
+                1.0
             };
+            debug!("released with velocity ({vel_x}, {vel_y})");
 
-            self.start_animation(fall_animation, "fall", ctx);
+            let body = Body::new(pos, (vel_x * scale, vel_y * scale));
+            self.dizzy_pending = speed >= DIZZY_SPEED;
+            self.start_physics(body, "thrown");
         }
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         let mut canvas = graphics::Canvas::from_frame(ctx, Color::new(0.0, 0.0, 0.0, 0.0));
-        self.animations.draw(ctx, &mut canvas);
+        match &self.drag_animation {
+            Some(drag_animation) if self.dragging => drag_animation.draw(&mut canvas),
+            _ => self.animations.draw(ctx, &mut canvas),
+        }
         canvas.finish(ctx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::ManualClock, companion::tests::write_pack, window::VirtualWindow};
+
+    #[test]
+    fn headless_companion_stays_on_screen() {
+        let pack = write_pack(
+            "headless",
+            r#"
+[animations]
+idle = [{ path = "idle1.png" }]
+walk = [{ path = "walk1.png" }]
+
+[transitions]
+initial = { walk_left = 0.5, walk_right = 0.5 }
+"#,
+        );
+        let monitor = (800.0, 600.0);
+        let (width, height) = (pack.companion.width, pack.companion.height);
+        let clock = Arc::new(ManualClock::new());
+        let mut app = CompanionApp::headless(
+            &pack,
+            pack.load_config().unwrap(),
+            7,
+            monitor,
+            clock.clone(),
+        )
+        .unwrap();
+        let mut window = VirtualWindow::new(monitor);
+
+        let mut walks = 0;
+        let mut first_behavior = None;
+        for _ in 0..3000 {
+            clock.advance(Duration::from_millis(40));
+            let started = app.step(&mut window).unwrap();
+            // the first behavior is only picked once the initial fall has landed
+            if started.is_some() && first_behavior.is_none() {
+                first_behavior = started;
+                assert_eq!(window.position.1, monitor.1 - height);
+            }
+            if matches!(started, Some(Behavior::WalkLeft | Behavior::WalkRight)) {
+                walks += 1;
+            }
+            assert!(
+                (0.0..=monitor.0 - width).contains(&window.position.0),
+                "left the screen at {:?}",
+                window.position
+            );
+        }
+
+        assert!(first_behavior.is_some(), "never landed");
+        assert!(walks > 0, "never walked");
+    }
+
+    #[test]
+    fn idle_sequence_keeps_the_idle_animation() {
+        let pack = write_pack(
+            "idle-sequence",
+            r#"
+[animations]
+idle = [{ path = "idle1.png" }]
+walk = [{ path = "walk1.png" }]
+wave = [{ path = "wave1.png" }]
+
+[transitions]
+initial = { idle = 1.0 }
+
+[sequences]
+idle = [{ play = "wave", seconds = 1.0 }]
+"#,
+        );
+        let clock = Arc::new(ManualClock::new());
+        let mut app = CompanionApp::headless(
+            &pack,
+            pack.load_config().unwrap(),
+            7,
+            (800.0, 600.0),
+            clock.clone(),
+        )
+        .unwrap();
+        let mut window = VirtualWindow::new((800.0, 600.0));
+
+        let mut started = None;
+        while started.is_none() {
+            clock.advance(Duration::from_millis(40));
+            started = app.step(&mut window).unwrap();
+        }
+        assert_eq!(started, Some(Behavior::Idle));
+        assert_eq!(app.animations.active.as_deref(), Some("sequence:idle"));
+
+        // once the sequence has played, the companion falls back to the idle animation
+        clock.advance(Duration::from_secs(1));
+        app.step(&mut window).unwrap();
+        assert_eq!(app.animations.active, None);
+        let mut names: Vec<_> = app
+            .animations
+            .animations
+            .keys()
+            .map(String::as_str)
+            .collect();
+        names.sort_unstable();
+        assert_eq!(names, ["fall", "idle", "sequence:idle"]);
+    }
+}
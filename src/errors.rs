@@ -1,4 +1,7 @@
-use std::{error::Error, process};
+use std::{error::Error, fmt, io, ops::RangeInclusive, path::PathBuf, process};
+
+use config::ConfigError;
+use ggez::GameError;
 
 /// Unwraps a `Result`, exiting the process with a given code on error.
 ///
@@ -31,3 +34,202 @@ pub fn unwrap_or_exit<T, E: Error>(result: Result<T, E>, code: i32) -> T {
         }
     }
 }
+
+/// Errors that can occur while loading the configuration and companions.
+///
+/// Unlike bugs, these are caused by the user's files, so each variant names the offending file and
+/// maps to its own exit code (see [`CompanionError::exit_code`]) for scripts wrapping the app.
+#[derive(Debug)]
+pub enum CompanionError {
+    /// `config.toml` could not be found in any of the searched locations.
+    ConfigNotFound {
+        /// Locations that were searched.
+        searched: Vec<PathBuf>,
+    },
+    /// A configuration file exists but could not be read or deserialized.
+    BadConfig {
+        /// Path to the configuration file.
+        path: PathBuf,
+        /// Underlying error from the `config` crate.
+        source: ConfigError,
+    },
+    /// No companion with the requested name is configured.
+    UnknownCompanion {
+        /// Requested companion name, or `None` if `config.toml` has no companions at all.
+        name: Option<String>,
+        /// Path to `config.toml`.
+        config: PathBuf,
+    },
+    /// A file referenced by the configuration could not be read.
+    MissingFile {
+        /// Path to the missing file.
+        path: PathBuf,
+        /// Underlying I/O error.
+        source: io::Error,
+    },
+    /// A sprite file exists but is not a valid image.
+    UndecodableImage {
+        /// Path to the image file.
+        path: PathBuf,
+        /// Underlying decoding error.
+        source: GameError,
+    },
+    /// `companion.toml` lacks an animation the companion can't work without.
+    MissingAnimation {
+        /// Name of the missing animation.
+        animation: String,
+        /// Path to `companion.toml`.
+        config: PathBuf,
+    },
+    /// An Aseprite export referenced by `companion.toml` is malformed or lacks a tag.
+    BadAseprite {
+        /// Path to the export's JSON data.
+        path: PathBuf,
+        /// What is wrong with it.
+        reason: String,
+    },
+    /// A pack given to one of the importers can't be converted.
+    ImportFailed {
+        /// Path to the offending file or directory.
+        path: PathBuf,
+        /// What is wrong with it.
+        reason: String,
+    },
+    /// An output file could not be written.
+    WriteFailed {
+        /// Path to the output file.
+        path: PathBuf,
+        /// Underlying I/O error.
+        source: io::Error,
+    },
+}
+
+impl CompanionError {
+    /// Range of all codes returned by [`CompanionError::exit_code`]. Starts at 3, since `clap`
+    /// exits with 2 on command-line mistakes.
+    pub const EXIT_CODES: RangeInclusive<i32> = 3..=11;
+
+    /// Exit code the process terminates with when this error reaches `main`.
+    ///
+    /// | Code | Error                                        |
+    /// |------|----------------------------------------------|
+    /// | 3    | [`CompanionError::ConfigNotFound`]           |
+    /// | 4    | [`CompanionError::BadConfig`]                |
+    /// | 5    | [`CompanionError::UnknownCompanion`]         |
+    /// | 6    | [`CompanionError::MissingFile`]              |
+    /// | 7    | [`CompanionError::UndecodableImage`]         |
+    /// | 8    | [`CompanionError::MissingAnimation`]         |
+    /// | 9    | [`CompanionError::BadAseprite`]              |
+    /// | 10   | [`CompanionError::ImportFailed`]             |
+    /// | 11   | [`CompanionError::WriteFailed`]              |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CompanionError::ConfigNotFound { .. } => 3,
+            CompanionError::BadConfig { .. } => 4,
+            CompanionError::UnknownCompanion { .. } => 5,
+            CompanionError::MissingFile { .. } => 6,
+            CompanionError::UndecodableImage { .. } => 7,
+            CompanionError::MissingAnimation { .. } => 8,
+            CompanionError::BadAseprite { .. } => 9,
+            CompanionError::ImportFailed { .. } => 10,
+            CompanionError::WriteFailed { .. } => 11,
+        }
+    }
+}
+
+impl fmt::Display for CompanionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompanionError::ConfigNotFound { searched } => {
+                let searched: Vec<String> = searched
+                    .iter()
+                    .map(|dir| dir.display().to_string())
+                    .collect();
+                write!(
+                    f,
+                    "config.toml not found, searched: {}. Create one there or pass --config <PATH>",
+                    searched.join(", ")
+                )
+            }
+            CompanionError::BadConfig { path, source } => {
+                write!(f, "{}: {source}", path.display())
+            }
+            CompanionError::UnknownCompanion {
+                name: Some(name),
+                config,
+            } => write!(
+                f,
+                "{}: no companion named `{name}`, run `desktop-companion list` to see the configured ones",
+                config.display()
+            ),
+            CompanionError::UnknownCompanion { name: None, config } => write!(
+                f,
+                "{}: no companions configured, add a [[companion]] table",
+                config.display()
+            ),
+            CompanionError::MissingFile { path, source } => {
+                write!(
+                    f,
+                    "{}: {source}, check that the path is correct",
+                    path.display()
+                )
+            }
+            CompanionError::UndecodableImage { path, source } => write!(
+                f,
+                "{}: not a valid image ({source}), re-export it as PNG",
+                path.display()
+            ),
+            CompanionError::MissingAnimation { animation, config } => write!(
+                f,
+                "{}: required animation `{animation}` is missing, add it under [animations]",
+                config.display()
+            ),
+            CompanionError::BadAseprite { path, reason } => write!(
+                f,
+                "{}: {reason}, re-export it with \"JSON Data\" and \"Tags\" enabled",
+                path.display()
+            ),
+            CompanionError::ImportFailed { path, reason } => {
+                write!(f, "{}: {reason}", path.display())
+            }
+            CompanionError::WriteFailed { path, source } => write!(
+                f,
+                "{}: {source}, check that the directory exists and is writable",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for CompanionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CompanionError::BadConfig { source, .. } => Some(source),
+            CompanionError::MissingFile { source, .. } => Some(source),
+            CompanionError::UndecodableImage { source, .. } => Some(source),
+            CompanionError::WriteFailed { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Unwraps a `Result` with a [`CompanionError`], exiting the process on error.
+///
+/// Works like [`unwrap_or_exit`], but the exit code is taken from
+/// [`CompanionError::exit_code`], and release builds don't ask the user to report the error,
+/// since it's caused by their own files.
+#[cfg_attr(debug_assertions, track_caller)]
+pub fn unwrap_or_exit_with_code<T>(result: Result<T, CompanionError>) -> T {
+    match result {
+        Ok(x) => x,
+        Err(e) => {
+            #[cfg(not(debug_assertions))]
+            eprintln!("{e}");
+
+            #[cfg(debug_assertions)]
+            eprintln!("{}: {e}", std::panic::Location::caller());
+
+            process::exit(e.exit_code())
+        }
+    }
+}
@@ -1,20 +1,40 @@
+use std::path::Path;
+use std::process;
+
+use clap::Parser;
 use ggez::ContextBuilder;
 use ggez::event;
 use log::debug;
 use log::info;
 
-use crate::companion::load_companion_config;
+use crate::cli::{Cli, Command};
+use crate::companion::find_config;
 use crate::companion::load_config;
+use crate::companion::{AppConfig, CompanionPack};
 use crate::core::CompanionApp;
-use crate::errors::unwrap_or_exit;
+use crate::errors::{CompanionError, unwrap_or_exit, unwrap_or_exit_with_code};
+use crate::simulate::{Format, Settings};
+use crate::supervisor::supervise;
+use crate::validate::validate_config;
 
 mod animation;
+mod aseprite;
 mod behavior;
+mod cli;
+mod clock;
 mod companion;
 mod core;
 mod errors;
+mod physics;
+mod shimeji;
+mod simulate;
+mod supervisor;
+mod validate;
+mod window;
 
 fn main() {
+    let cli = Cli::parse();
+
     fern::Dispatch::new()
         .format(|out, message, record| {
             out.finish(format_args!(
@@ -25,7 +45,7 @@ fn main() {
                 message
             ))
         })
-        .level(log::LevelFilter::Debug)
+        .level(cli.log_level)
         .level_for("wgpu", log::LevelFilter::Error)
         .level_for("wgpu_core", log::LevelFilter::Error)
         .level_for("naga", log::LevelFilter::Error)
@@ -33,40 +53,204 @@ fn main() {
         .apply()
         .unwrap();
 
-    let config = unwrap_or_exit(load_config(), 1);
+    // importing creates a companion, so it must work without a configuration
+    if let Some(Command::ImportShimeji {
+        images,
+        output,
+        conf,
+        name,
+        force,
+    }) = &cli.command
+    {
+        import_shimeji(images, output, conf.as_deref(), name.as_deref(), *force);
+        return;
+    }
+
+    let config_path = unwrap_or_exit_with_code(find_config(cli.config.as_deref()));
+    info!("Using {}", config_path.display());
+
+    let config = unwrap_or_exit_with_code(load_config(&config_path));
     info!("{:#?}", config);
 
-    // `ggez` is synchronous, so at this moment we can spawn only one companion.
-    // TODO: Make a command line interface so we can spawn new process for every companion instead
-    // of threads.
-    config.companion.iter().for_each(|c| {
-        debug!("Loading {}", c.name);
-        let (mut ctx, event_loop) = ContextBuilder::new("desktop-companion", "w1ltshire")
-            .window_mode(
-                ggez::conf::WindowMode::default()
-                    .transparent(true)
-                    .borderless(true)
-                    .dimensions(c.width, c.height),
-            )
-            .build()
-            .expect("Could not create ggez context");
-
-        debug!(
-            "{}/config/{}/companion.toml",
-            std::env::current_dir().unwrap().to_str().unwrap(),
-            &c.path
-        );
-
-        let companion_config = unwrap_or_exit(
-            load_companion_config(&format!(
-                "{}/config/{}/companion.toml",
-                std::env::current_dir().unwrap().to_str().unwrap(),
-                &c.path
-            )),
+    match &cli.command {
+        Some(Command::Run { companion }) => {
+            run_companion(&config_path, &config, companion.as_deref(), cli.seed)
+        }
+        Some(Command::List) => list_companions(&config),
+        Some(Command::Validate) => validate(&config_path, &config),
+        Some(Command::Simulate {
+            output,
+            companion,
+            duration,
+            step,
+            screen,
+            format,
+        }) => simulate_companion(
+            &config_path,
+            &config,
+            companion.as_deref(),
+            cli.seed,
+            Settings {
+                duration: *duration,
+                step: *step,
+                screen: *screen,
+            },
+            output,
+            format.unwrap_or_else(|| Format::of(output)),
+        ),
+        Some(Command::ImportShimeji { .. }) => unreachable!("handled before loading the config"),
+        None => unwrap_or_exit(
+            supervise(&config, &cli.forwarded_args(&config_path), cli.seed),
             1,
-        );
+        ),
+    }
+}
+
+/// Prints the configured companions.
+fn list_companions(config: &AppConfig) {
+    for c in &config.companion {
+        println!("{}\t{}\t{}x{}", c.name, c.path, c.width, c.height);
+    }
+}
 
-        let app = CompanionApp::new(&mut ctx, c.clone(), companion_config);
-        event::run(ctx, event_loop, app);
+/// Validates the configuration and every companion pack, printing all problems found.
+///
+/// Exits with code 1 if there are any problems.
+fn validate(config_path: &Path, config: &AppConfig) {
+    let problems = validate_config(config_path, config);
+    for problem in &problems {
+        println!("{problem}");
+    }
+
+    if problems.is_empty() {
+        println!("{}: ok", config_path.display());
+    } else {
+        println!("{} problem(s) found", problems.len());
+        process::exit(1);
+    }
+}
+
+/// Converts a Shimeji image set into a companion and prints its `config.toml` entry.
+///
+/// # Arguments
+/// * `images` - Directory containing the Shimeji images.
+/// * `output` - Directory to write the companion to.
+/// * `conf` - Directory containing `actions.xml` and `behaviors.xml`, if not next to the images.
+/// * `name` - Name of the companion, or `None` for the name of `output`.
+/// * `force` - Whether to overwrite an existing `companion.toml`.
+fn import_shimeji(
+    images: &Path,
+    output: &Path,
+    conf: Option<&Path>,
+    name: Option<&str>,
+    force: bool,
+) {
+    let import = unwrap_or_exit_with_code(shimeji::import(images, conf));
+    unwrap_or_exit_with_code(import.write(output, force));
+
+    let name = name.map(str::to_string).unwrap_or_else(|| {
+        output.file_name().map_or("shimeji".to_string(), |name| {
+            name.to_string_lossy().into_owned()
+        })
     });
+    println!(
+        "Imported {} animations to {}. Add the companion to config.toml, `path` may be made \
+         relative to its directory:\n",
+        import.animations.len(),
+        output.display()
+    );
+    println!("[[companion]]");
+    println!("name = {name:?}");
+    println!("path = {:?}", output.display().to_string());
+    println!("width = {}", import.size.0);
+    println!("height = {}", import.size.1);
+    println!("walkspeed = {}", import.walkspeed);
+}
+
+/// Runs a single companion in the current process.
+///
+/// `ggez` is synchronous and never returns from its event loop, so every companion needs its own
+/// process. See [`supervisor`] for spawning all of them.
+///
+/// # Arguments
+/// * `config_path` - Path to `config.toml`.
+/// * `config` - Application configuration.
+/// * `name` - Name of the companion to run, or `None` for the first configured one.
+/// * `seed` - Seed for the companion's RNG, or `None` for a random one.
+fn run_companion(config_path: &Path, config: &AppConfig, name: Option<&str>, seed: Option<u64>) {
+    let pack = unwrap_or_exit_with_code(find_pack(config_path, config, name));
+    let seed = resolve_seed(seed);
+
+    let c = &pack.companion;
+    debug!("Loading {} from {}", c.name, pack.root().display());
+    let companion_config = unwrap_or_exit_with_code(pack.load_config());
+
+    let (mut ctx, event_loop) = ContextBuilder::new("desktop-companion", "w1ltshire")
+        .window_mode(
+            ggez::conf::WindowMode::default()
+                .transparent(true)
+                .borderless(true)
+                .dimensions(c.width, c.height),
+        )
+        .build()
+        .expect("Could not create ggez context");
+
+    let app = unwrap_or_exit_with_code(CompanionApp::new(&mut ctx, &pack, companion_config, seed));
+    event::run(ctx, event_loop, app);
+}
+
+/// Simulates a companion without a window, writing its timeline and printing a summary.
+///
+/// # Arguments
+/// * `config_path` - Path to `config.toml`.
+/// * `config` - Application configuration.
+/// * `name` - Name of the companion to simulate, or `None` for the first configured one.
+/// * `seed` - Seed for the companion's RNG, or `None` for a random one.
+/// * `settings` - Length, resolution and screen size of the simulation.
+/// * `output` - File to write the timeline to.
+/// * `format` - Format of the timeline.
+fn simulate_companion(
+    config_path: &Path,
+    config: &AppConfig,
+    name: Option<&str>,
+    seed: Option<u64>,
+    settings: Settings,
+    output: &Path,
+    format: Format,
+) {
+    let pack = unwrap_or_exit_with_code(find_pack(config_path, config, name));
+    let seed = resolve_seed(seed);
+    let companion_config = unwrap_or_exit_with_code(pack.load_config());
+
+    let simulation =
+        unwrap_or_exit_with_code(simulate::simulate(&pack, companion_config, seed, settings));
+    unwrap_or_exit_with_code(simulation.write(output, format));
+
+    println!("{}", simulation.summary);
+    println!("Timeline written to {}", output.display());
+}
+
+/// Finds the pack of the companion called `name`, or of the first one if `name` is `None`.
+fn find_pack(
+    config_path: &Path,
+    config: &AppConfig,
+    name: Option<&str>,
+) -> Result<CompanionPack, CompanionError> {
+    config
+        .packs(config_path)
+        .into_iter()
+        .find(|pack| name.is_none_or(|name| pack.companion.name == name))
+        .ok_or_else(|| CompanionError::UnknownCompanion {
+            name: name.map(str::to_string),
+            config: config_path.to_path_buf(),
+        })
+}
+
+/// Returns `seed`, or a random one if it's `None`.
+///
+/// The seed is logged even if it's random, so any run can be replayed.
+fn resolve_seed(seed: Option<u64>) -> u64 {
+    let seed = seed.unwrap_or_else(rand::random);
+    info!("Using seed {seed}, pass `--seed {seed}` to replay this run");
+    seed
 }
@@ -0,0 +1,430 @@
+//! Module `physics` provides a tiny rigid-body simulation for the companion's window.
+//!
+//! It handles:
+//! - Integrating position and velocity under gravity
+//! - Collisions with the ground and the monitor's side edges
+//! - Bouncing (restitution) and sliding to a stop on the ground (friction)
+//!
+//! Positions are in logical pixels and velocities in pixels per second, with `y` growing
+//! downwards like window coordinates.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+
+use crate::clock::SharedClock;
+
+/// Vertical speed below which a bounce on the ground is absorbed and the body comes to rest.
+const REST_SPEED: f32 = 60.0;
+
+/// Horizontal speed below which a body sliding on the ground stops.
+const STOP_SPEED: f32 = 5.0;
+
+/// Largest time step used for a single integration step, in seconds.
+///
+/// Longer frames are split into several steps so fast bodies don't tunnel through edges.
+const MAX_STEP: f32 = 1.0 / 120.0;
+
+/// How far back [`VelocityTracker`] looks when estimating the pointer velocity.
+const TRACKING_WINDOW: Duration = Duration::from_millis(100);
+
+/// Physical parameters of a companion.
+///
+/// Parameters that would keep a body from ever coming to rest are rejected when deserializing,
+/// see [`PhysicsConfig::check`].
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(try_from = "RawPhysicsConfig")]
+pub struct PhysicsConfig {
+    /// Gravitational acceleration in pixels per second squared.
+    pub gravity: f32,
+    /// Fraction of the speed kept after hitting the ground or a wall, from `0.0` up to but
+    /// excluding `1.0`.
+    pub restitution: f32,
+    /// Friction coefficient slowing the body down while it slides on the ground.
+    pub friction: f32,
+}
+
+impl PhysicsConfig {
+    /// Checks that a body simulated with these parameters comes to rest.
+    ///
+    /// Gravity has to pull downwards, so falling bodies land, and bounces have to lose speed,
+    /// so bodies stop bouncing.
+    ///
+    /// # Returns
+    /// * `Ok(())` if gravity is positive, restitution is in `0.0..1.0` and friction isn't
+    ///   negative.
+    /// * `Err(String)` describing the first parameter that isn't.
+    pub fn check(&self) -> Result<(), String> {
+        if !self.gravity.is_finite() || self.gravity <= 0.0 {
+            return Err(format!(
+                "physics `gravity` is {}, it must be positive",
+                self.gravity
+            ));
+        }
+        if !(0.0..1.0).contains(&self.restitution) {
+            return Err(format!(
+                "physics `restitution` is {}, it must be at least 0 and below 1",
+                self.restitution
+            ));
+        }
+        if !self.friction.is_finite() || self.friction < 0.0 {
+            return Err(format!(
+                "physics `friction` is {}, it must not be negative",
+                self.friction
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// [`PhysicsConfig`] as written in `config.toml`, before it is checked.
+#[derive(Deserialize)]
+#[serde(default)]
+struct RawPhysicsConfig {
+    gravity: f32,
+    restitution: f32,
+    friction: f32,
+}
+
+impl Default for RawPhysicsConfig {
+    fn default() -> Self {
+        let PhysicsConfig {
+            gravity,
+            restitution,
+            friction,
+        } = PhysicsConfig::default();
+        Self {
+            gravity,
+            restitution,
+            friction,
+        }
+    }
+}
+
+impl TryFrom<RawPhysicsConfig> for PhysicsConfig {
+    type Error = String;
+
+    fn try_from(raw: RawPhysicsConfig) -> Result<Self, Self::Error> {
+        let config = Self {
+            gravity: raw.gravity,
+            restitution: raw.restitution,
+            friction: raw.friction,
+        };
+        config.check()?;
+        Ok(config)
+    }
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        Self {
+            gravity: 2000.0,
+            restitution: 0.3,
+            friction: 0.8,
+        }
+    }
+}
+
+/// Area the body is allowed to move in.
+///
+/// There is no ceiling, so bodies can fly above the top of the monitor and fall back.
+#[derive(Debug, Clone, Copy)]
+pub struct Bounds {
+    /// Leftmost allowed `x`.
+    pub min_x: f32,
+    /// Rightmost allowed `x`.
+    pub max_x: f32,
+    /// `y` of the ground.
+    pub ground_y: f32,
+}
+
+/// Collisions that happened during a [`Body::step`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Contacts {
+    /// The body hit the ground.
+    pub ground: bool,
+    /// The body hit the left or right edge.
+    pub wall: bool,
+}
+
+/// A point mass with a position and a velocity.
+#[derive(Debug, Clone, Copy)]
+pub struct Body {
+    /// Position `(x, y)` in logical pixels.
+    pub pos: (f32, f32),
+    /// Velocity `(x, y)` in pixels per second.
+    pub vel: (f32, f32),
+    /// Whether the body is standing on the ground.
+    pub grounded: bool,
+}
+
+impl Body {
+    /// Creates a body at `pos` moving with `vel`.
+    pub fn new(pos: (f32, f32), vel: (f32, f32)) -> Self {
+        Self {
+            pos,
+            vel,
+            grounded: false,
+        }
+    }
+
+    /// Returns `true` once the body stands still on the ground.
+    pub fn is_resting(&self) -> bool {
+        self.grounded && self.vel.0 == 0.0
+    }
+
+    /// Advances the simulation by `dt` seconds.
+    ///
+    /// # Arguments
+    /// * `dt` - Elapsed time in seconds.
+    /// * `config` - Physical parameters of the companion.
+    /// * `bounds` - Area the body is confined to.
+    ///
+    /// # Returns
+    /// The collisions that happened during this step.
+    pub fn step(&mut self, dt: f32, config: &PhysicsConfig, bounds: &Bounds) -> Contacts {
+        let mut contacts = Contacts::default();
+        let mut remaining = dt.max(0.0);
+
+        while remaining > 0.0 {
+            let h = remaining.min(MAX_STEP);
+            remaining -= h;
+
+            let step = self.integrate(h, config, bounds);
+            contacts.ground |= step.ground;
+            contacts.wall |= step.wall;
+        }
+
+        contacts
+    }
+
+    /// Single semi-implicit Euler step followed by collision resolution.
+    fn integrate(&mut self, h: f32, config: &PhysicsConfig, bounds: &Bounds) -> Contacts {
+        let mut contacts = Contacts::default();
+
+        if self.grounded {
+            // Coulomb friction: constant deceleration opposite to the sliding direction
+            let decel = config.friction * config.gravity * h;
+            self.vel.0 = if self.vel.0.abs() <= decel.max(STOP_SPEED) {
+                0.0
+            } else {
+                self.vel.0 - decel * self.vel.0.signum()
+            };
+        } else {
+            self.vel.1 += config.gravity * h;
+        }
+
+        self.pos.0 += self.vel.0 * h;
+        self.pos.1 += self.vel.1 * h;
+
+        if self.pos.1 >= bounds.ground_y {
+            self.pos.1 = bounds.ground_y;
+            if !self.grounded {
+                contacts.ground = true;
+                self.vel.1 = -self.vel.1 * config.restitution;
+                if self.vel.1.abs() < REST_SPEED {
+                    self.vel.1 = 0.0;
+                    self.grounded = true;
+                }
+            }
+        }
+
+        if self.pos.0 < bounds.min_x || self.pos.0 > bounds.max_x {
+            self.pos.0 = self.pos.0.clamp(bounds.min_x, bounds.max_x);
+            self.vel.0 = -self.vel.0 * config.restitution;
+            contacts.wall = true;
+        }
+
+        contacts
+    }
+}
+
+/// Estimates the velocity of the pointer from its recent positions.
+///
+/// Only samples from the last [`TRACKING_WINDOW`] are kept, so the estimate reflects how the
+/// pointer was moving right before it was released rather than over the whole drag.
+#[derive(Debug)]
+pub struct VelocityTracker {
+    samples: VecDeque<(Instant, (f32, f32))>,
+    clock: SharedClock,
+}
+
+impl VelocityTracker {
+    /// Creates a tracker without samples.
+    pub fn new(clock: SharedClock) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            clock,
+        }
+    }
+
+    /// Forgets all recorded samples.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Records the pointer at `pos` (in screen coordinates) at the current time.
+    pub fn record(&mut self, pos: (f32, f32)) {
+        let now = self.clock.now();
+        self.samples.push_back((now, pos));
+
+        while let Some((time, _)) = self.samples.front()
+            && now.duration_since(*time) > TRACKING_WINDOW
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Returns the average velocity over the recorded samples in pixels per second.
+    ///
+    /// Returns zero if there are too few samples or the pointer stopped moving before release.
+    pub fn velocity(&self) -> (f32, f32) {
+        let (Some((first_time, first)), Some((last_time, last))) =
+            (self.samples.front(), self.samples.back())
+        else {
+            return (0.0, 0.0);
+        };
+
+        let dt = last_time.duration_since(*first_time).as_secs_f32();
+        if dt <= 0.0 || self.clock.elapsed(*last_time) > TRACKING_WINDOW {
+            return (0.0, 0.0);
+        }
+
+        ((last.0 - first.0) / dt, (last.1 - first.1) / dt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::clock::ManualClock;
+
+    const BOUNDS: Bounds = Bounds {
+        min_x: 0.0,
+        max_x: 100.0,
+        ground_y: 500.0,
+    };
+
+    fn config(restitution: f32, friction: f32) -> PhysicsConfig {
+        PhysicsConfig {
+            gravity: 2000.0,
+            restitution,
+            friction,
+        }
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-2,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn ground_bounce_keeps_restitution_of_the_speed() {
+        let mut body = Body::new((50.0, 495.0), (0.0, 1000.0));
+
+        let contacts = body.step(MAX_STEP, &config(0.5, 0.8), &BOUNDS);
+
+        let impact = 1000.0 + 2000.0 * MAX_STEP;
+        assert!(contacts.ground && !contacts.wall);
+        assert_eq!(body.pos.1, BOUNDS.ground_y);
+        assert_close(body.vel.1, -impact * 0.5);
+        assert!(!body.grounded);
+    }
+
+    #[test]
+    fn wall_reflects_horizontal_speed() {
+        let mut body = Body::new((95.0, 0.0), (1200.0, 0.0));
+
+        let contacts = body.step(MAX_STEP, &config(0.5, 0.8), &BOUNDS);
+
+        assert!(contacts.wall && !contacts.ground);
+        assert_eq!(body.pos.0, BOUNDS.max_x);
+        assert_close(body.vel.0, -600.0);
+    }
+
+    #[test]
+    fn friction_stops_a_sliding_body() {
+        let mut body = Body::new((0.0, BOUNDS.ground_y), (300.0, 0.0));
+        body.grounded = true;
+        let bounds = Bounds {
+            max_x: 1000.0,
+            ..BOUNDS
+        };
+
+        // decelerates at 0.8 * 2000 px/s², so it stops after 0.1875 s
+        body.step(0.1, &config(0.5, 0.8), &bounds);
+        assert!(!body.is_resting());
+        assert_close(body.vel.0, 140.0);
+
+        body.step(0.1, &config(0.5, 0.8), &bounds);
+        assert!(body.is_resting());
+        // about v² / 2a = 28 px, a little less since the speed drops before each move
+        assert!((26.0..29.0).contains(&body.pos.0), "slid to {}", body.pos.0);
+    }
+
+    #[test]
+    fn dropped_body_comes_to_rest() {
+        let mut body = Body::new((50.0, 0.0), (200.0, 0.0));
+        let config = config(0.6, 0.8);
+
+        let mut bounces = 0;
+        for _ in 0..600 {
+            if body.step(1.0 / 60.0, &config, &BOUNDS).ground {
+                bounces += 1;
+            }
+            if body.is_resting() {
+                break;
+            }
+        }
+
+        assert!(body.is_resting(), "still moving after 10 s: {body:?}");
+        assert!(bounces > 1, "never bounced");
+        assert_eq!(body.pos.1, BOUNDS.ground_y);
+        assert!((BOUNDS.min_x..=BOUNDS.max_x).contains(&body.pos.0));
+    }
+
+    #[test]
+    fn config_that_never_rests_is_rejected() {
+        let parse = |json: &str| serde_json::from_str::<PhysicsConfig>(json);
+
+        assert!(parse("{}").is_ok());
+        assert!(parse(r#"{ "restitution": 0.9, "friction": 0.0 }"#).is_ok());
+        assert!(parse(r#"{ "restitution": 1.0 }"#).is_err());
+        assert!(parse(r#"{ "restitution": -0.1 }"#).is_err());
+        assert!(parse(r#"{ "gravity": 0.0 }"#).is_err());
+        assert!(parse(r#"{ "gravity": -2000.0 }"#).is_err());
+        assert!(parse(r#"{ "friction": -1.0 }"#).is_err());
+    }
+
+    #[test]
+    fn velocity_of_recent_samples() {
+        let clock = Arc::new(ManualClock::new());
+        let mut tracker = VelocityTracker::new(clock.clone());
+        assert_eq!(tracker.velocity(), (0.0, 0.0));
+
+        // samples older than the tracking window don't count
+        tracker.record((1000.0, 1000.0));
+        clock.advance(Duration::from_millis(200));
+        tracker.record((0.0, 0.0));
+        assert_eq!(
+            tracker.velocity(),
+            (0.0, 0.0),
+            "a single sample has no velocity"
+        );
+        clock.advance(Duration::from_millis(50));
+        tracker.record((10.0, 5.0));
+        assert_close(tracker.velocity().0, 200.0);
+        assert_close(tracker.velocity().1, 100.0);
+
+        // holding the pointer still before releasing it drops the velocity
+        clock.advance(Duration::from_millis(150));
+        assert_eq!(tracker.velocity(), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,855 @@
+//! Module `shimeji` imports Shimeji image sets as companions.
+//!
+//! A Shimeji pack is a directory of `shime1.png` to `shime46.png` and usually a `conf` directory
+//! with `actions.xml` and `behaviors.xml`. The importer handles:
+//! - Turning the poses of each action into an animation, with Shimeji's standard actions mapped
+//!   to the animation names this application uses (`Stand` becomes `idle`, `Pinched` becomes
+//!   `drag`, ...)
+//! - Falling back to the standard frame numbering for animations `actions.xml` doesn't provide
+//! - Summing up the behavior frequencies of `behaviors.xml` into a transition matrix
+//! - Writing a `companion.toml` and copying the used images next to it
+//!
+//! Only English (Shimeji-ee) configuration files are understood. Shimeji actions that need
+//! walls, ceilings or other windows have no counterpart here and are imported as plain
+//! animations without behaviors.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use xml::{
+    attribute::OwnedAttribute,
+    reader::{EventReader, XmlEvent},
+};
+
+use crate::{
+    behavior::Behavior,
+    companion::{DEFAULT_FRAME_DURATION_MS, Sprite},
+    errors::CompanionError,
+};
+
+/// Length of a Shimeji tick in milliseconds. Shimeji runs at 25 ticks per second.
+const TICK_MS: u64 = 40;
+
+/// Walking speed in pixels per tick used when `actions.xml` doesn't declare one.
+const DEFAULT_WALK_VELOCITY: f32 = 2.0;
+
+/// Shimeji actions whose animations are used under another name.
+const ACTION_ANIMATIONS: &[(&str, &str)] = &[
+    ("Stand", "idle"),
+    ("Walk", "walk"),
+    ("Falling", "fall"),
+    ("Jumping", "jump"),
+    ("Thrown", "thrown"),
+    ("Pinched", "drag"),
+    ("Bouncing", "dizzy"),
+];
+
+/// Animations of the standard Shimeji numbering, as `(image number, ticks)` pairs.
+///
+/// Used for every animation `actions.xml` doesn't provide. `drag` lists the poses from swinging
+/// left to swinging right, like [`crate::animation::drag::DragAnimation`] expects.
+const DEFAULT_ANIMATIONS: &[(&str, &[(u32, u64)])] = &[
+    ("idle", &[(1, 250)]),
+    ("walk", &[(1, 6), (2, 6), (1, 6), (3, 6)]),
+    ("fall", &[(4, 250)]),
+    ("jump", &[(22, 250)]),
+    ("thrown", &[(4, 250)]),
+    ("drag", &[(9, 5), (7, 5), (1, 5), (8, 5), (10, 5)]),
+    ("dizzy", &[(18, 4), (19, 4)]),
+    ("sit", &[(11, 250)]),
+    ("sprawl", &[(21, 250)]),
+    ("creep", &[(20, 28), (21, 28)]),
+];
+
+/// Single image of a Shimeji action.
+#[derive(Debug, Clone)]
+pub struct Pose {
+    /// Image path relative to the image directory.
+    pub image: String,
+    /// Movement in pixels per tick while the pose is shown.
+    pub velocity: (f32, f32),
+    /// How long the pose is shown in ticks.
+    pub duration: u64,
+}
+
+/// Action declared in `actions.xml`.
+#[derive(Debug, Clone)]
+pub struct Action {
+    /// Name of the action.
+    pub name: String,
+    /// Alternative animations of the action, each picked by Shimeji under its own condition.
+    pub animations: Vec<Vec<Pose>>,
+}
+
+/// Behavior declared in `behaviors.xml`.
+#[derive(Debug, Clone)]
+pub struct ShimejiBehavior {
+    /// Name of the behavior.
+    pub name: String,
+    /// Name of the action the behavior performs.
+    pub action: String,
+    /// Relative frequency of the behavior.
+    pub frequency: f32,
+    /// Behaviors that may follow this one, if it restricts them.
+    pub next: Option<NextBehaviors>,
+}
+
+/// `NextBehaviorList` of a [`ShimejiBehavior`].
+#[derive(Debug, Clone)]
+pub struct NextBehaviors {
+    /// Whether the listed behaviors are added to the global ones instead of replacing them.
+    pub add: bool,
+    /// Names and frequencies of the listed behaviors.
+    pub behaviors: Vec<(String, f32)>,
+}
+
+/// Companion generated from a Shimeji pack, ready to be written with [`ShimejiImport::write`].
+#[derive(Debug)]
+pub struct ShimejiImport {
+    /// Directory the images are copied from.
+    pub images: PathBuf,
+    /// Animations keyed by name.
+    pub animations: BTreeMap<String, Vec<Sprite>>,
+    /// Transition matrix as written under `[transitions]`, or `None` without `behaviors.xml`.
+    pub transitions: Option<BTreeMap<String, BTreeMap<Behavior, f32>>>,
+    /// Size of the `idle` sprite in pixels.
+    pub size: (u32, u32),
+    /// Walking speed in pixels per second.
+    pub walkspeed: f32,
+}
+
+/// Imports a Shimeji pack.
+///
+/// # Arguments
+/// * `images` - Directory containing `shime1.png` and the other images.
+/// * `conf` - Directory containing `actions.xml` and `behaviors.xml`. Defaults to the first of
+///   `images/conf`, `images`, `images/../conf` and `images/../../conf` that has them.
+///
+/// # Returns
+/// * `Ok(ShimejiImport)` with the generated companion.
+/// * `Err(CompanionError)` if the configuration files are malformed or images are missing.
+pub fn import(images: &Path, conf: Option<&Path>) -> Result<ShimejiImport, CompanionError> {
+    let conf_dirs = match conf {
+        Some(conf) => vec![conf.to_path_buf()],
+        None => vec![
+            images.join("conf"),
+            images.to_path_buf(),
+            images.join("../conf"),
+            images.join("../../conf"),
+        ],
+    };
+    let find = |file: &str| {
+        conf_dirs
+            .iter()
+            .map(|dir| dir.join(file))
+            .find(|path| path.is_file())
+    };
+
+    let actions = match find("actions.xml") {
+        Some(path) => read_actions(&path)?,
+        None => Vec::new(),
+    };
+    let behaviors = match find("behaviors.xml") {
+        Some(path) => Some(read_behaviors(&path)?),
+        None => None,
+    };
+
+    let mut animations = BTreeMap::new();
+    for action in &actions {
+        let name = animation_name(&action.name);
+        // Shimeji picks the pinched pose by the pointer's position, one animation per pose
+        let poses: Vec<&Pose> = if name == "drag" {
+            action.animations.iter().filter_map(|a| a.first()).collect()
+        } else {
+            action.animations.first().into_iter().flatten().collect()
+        };
+        if poses.is_empty() {
+            continue;
+        }
+
+        let mut sprites = Vec::new();
+        for pose in poses {
+            let path = images.join(&pose.image);
+            if !path.is_file() {
+                return Err(CompanionError::MissingFile {
+                    path,
+                    source: std::io::ErrorKind::NotFound.into(),
+                });
+            }
+            sprites.push(Sprite {
+                path: pose.image.clone(),
+                duration_ms: pose.duration * TICK_MS,
+                rect: None,
+            });
+        }
+        animations.entry(name).or_insert(sprites);
+    }
+
+    for (name, frames) in DEFAULT_ANIMATIONS {
+        let sprites: Vec<Sprite> = frames
+            .iter()
+            .map(|(number, ticks)| Sprite {
+                path: format!("shime{number}.png"),
+                duration_ms: ticks * TICK_MS,
+                rect: None,
+            })
+            .collect();
+        if !animations.contains_key(*name) && sprites.iter().all(|s| images.join(&s.path).is_file())
+        {
+            animations.insert(name.to_string(), sprites);
+        }
+    }
+
+    let idle = animations
+        .get("idle")
+        .and_then(|sprites| sprites.first())
+        .ok_or_else(|| CompanionError::ImportFailed {
+            path: images.to_path_buf(),
+            reason: "no `Stand` action and no shime1.png, is this a Shimeji image directory?"
+                .to_string(),
+        })?;
+    let idle_path = images.join(&idle.path);
+    let size = image::image_dimensions(&idle_path).map_err(|e| CompanionError::ImportFailed {
+        path: idle_path,
+        reason: format!("can't be decoded: {e}"),
+    })?;
+
+    let walk_velocity = actions
+        .iter()
+        .filter(|action| action.name == "Walk")
+        .flat_map(|action| action.animations.iter().flatten())
+        .map(|pose| pose.velocity.0.abs())
+        .find(|velocity| *velocity > 0.0)
+        .unwrap_or(DEFAULT_WALK_VELOCITY);
+
+    Ok(ShimejiImport {
+        images: images.to_path_buf(),
+        animations,
+        transitions: behaviors.and_then(|behaviors| transitions(&behaviors)),
+        size,
+        walkspeed: walk_velocity * (1000 / TICK_MS) as f32,
+    })
+}
+
+/// Returns the animation name used for a Shimeji action.
+///
+/// Standard actions are renamed according to [`ACTION_ANIMATIONS`], all others are converted
+/// to snake case, e.g. `SitAndLookUp` becomes `sit_and_look_up`.
+fn animation_name(action: &str) -> String {
+    if let Some((_, name)) = ACTION_ANIMATIONS.iter().find(|(a, _)| *a == action) {
+        return name.to_string();
+    }
+
+    let mut name = String::new();
+    for c in action.chars() {
+        if c.is_ascii_uppercase() && !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_lowercase());
+        } else if !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    name.trim_end_matches('_').to_string()
+}
+
+/// Returns the behaviors a Shimeji action corresponds to.
+///
+/// Actions that need walls, ceilings, other windows or the pointer have no counterpart and map
+/// to nothing. Walking is split evenly between both directions.
+fn behaviors_of(action: &str) -> &'static [Behavior] {
+    // Shimeji calls other windows "IE", after Internet Explorer
+    let needs_window = action.contains("IE");
+    let action = action.to_ascii_lowercase();
+    let unsupported = [
+        "wall", "ceiling", "climb", "grab", "pinch", "drag", "thrown", "chase", "split", "breed",
+        "clone",
+    ];
+    if needs_window || unsupported.iter().any(|word| action.contains(word)) {
+        &[]
+    } else if action.contains("jump") {
+        &[Behavior::Jump]
+    } else if action.contains("fall") {
+        &[Behavior::Fall]
+    } else if ["walk", "run", "dash", "creep"]
+        .iter()
+        .any(|word| action.contains(word))
+    {
+        &[Behavior::WalkLeft, Behavior::WalkRight]
+    } else {
+        &[Behavior::Idle]
+    }
+}
+
+/// Builds a transition matrix from Shimeji behaviors.
+///
+/// The `initial` row holds the global frequencies. A state gets a row of its own if one of the
+/// Shimeji behaviors mapping to it has a `NextBehaviorList`, mixing what may follow each of those
+/// behaviors by their frequencies.
+///
+/// Returns `None` if none of the behaviors has a counterpart.
+fn transitions(behaviors: &[ShimejiBehavior]) -> Option<BTreeMap<String, BTreeMap<Behavior, f32>>> {
+    let action_of = |name: &str| {
+        behaviors
+            .iter()
+            .find(|b| b.name == name)
+            .map_or(name.to_string(), |b| b.action.clone())
+    };
+    let weigh = |weights: &mut BTreeMap<Behavior, f32>, action: &str, frequency: f32| {
+        let targets = behaviors_of(action);
+        for behavior in targets {
+            *weights.entry(*behavior).or_default() += frequency / targets.len() as f32;
+        }
+    };
+
+    let mut global = BTreeMap::new();
+    for behavior in behaviors {
+        weigh(&mut global, &behavior.action, behavior.frequency);
+    }
+
+    let mut table = BTreeMap::new();
+    for state in Behavior::ALL {
+        let sources: Vec<&ShimejiBehavior> = behaviors
+            .iter()
+            .filter(|b| behaviors_of(&b.action).contains(&state))
+            .collect();
+        if sources.iter().all(|b| b.next.is_none()) {
+            // the state falls back to the `initial` row
+            continue;
+        }
+
+        // frequent behaviors have more say in what follows the state
+        let total: f32 = sources.iter().map(|b| b.frequency).sum();
+        let mut row = BTreeMap::new();
+        for source in sources {
+            let share = if total > 0.0 {
+                source.frequency / total
+            } else {
+                1.0
+            };
+            let mut next = match &source.next {
+                Some(next) if !next.add => BTreeMap::new(),
+                _ => global.clone(),
+            };
+            for (name, frequency) in source.next.iter().flat_map(|next| &next.behaviors) {
+                weigh(&mut next, &action_of(name), *frequency);
+            }
+            for (target, weight) in normalized(next) {
+                *row.entry(target).or_default() += weight * share;
+            }
+        }
+
+        let row = normalized(row);
+        if !row.is_empty() {
+            table.insert(state.name().to_string(), row);
+        }
+    }
+
+    let global = normalized(global);
+    if global.is_empty() {
+        return None;
+    }
+    table.insert("initial".to_string(), global);
+    Some(table)
+}
+
+/// Scales weights to sum up to one, rounded to three decimals, dropping the ones that end up
+/// zero since `companion.toml` only accepts positive weights.
+fn normalized(weights: BTreeMap<Behavior, f32>) -> BTreeMap<Behavior, f32> {
+    let total: f32 = weights.values().sum();
+    if total <= 0.0 {
+        return BTreeMap::new();
+    }
+    weights
+        .into_iter()
+        .map(|(behavior, weight)| (behavior, (weight / total * 1000.0).round() / 1000.0))
+        .filter(|(_, weight)| *weight > 0.0)
+        .collect()
+}
+
+/// Reads every event of an XML file.
+fn read_xml(path: &Path) -> Result<Vec<XmlEvent>, CompanionError> {
+    let file = fs::File::open(path).map_err(|source| CompanionError::MissingFile {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    EventReader::new(BufReader::new(file))
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .map_err(|e| CompanionError::ImportFailed {
+            path: path.to_path_buf(),
+            reason: e.to_string(),
+        })
+}
+
+/// Returns the value of the attribute `name`.
+fn attribute<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|a| a.name.local_name == name)
+        .map(|a| a.value.as_str())
+}
+
+/// Parses a number attribute such as `Frequency="100"` or `Duration="6"`.
+fn number<T: std::str::FromStr>(path: &Path, value: &str) -> Result<T, CompanionError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| CompanionError::ImportFailed {
+            path: path.to_path_buf(),
+            reason: format!("`{value}` is not a number"),
+        })
+}
+
+/// Reads the actions declared in `actions.xml`.
+///
+/// Actions without poses, such as sequences of other actions, are skipped.
+pub fn read_actions(path: &Path) -> Result<Vec<Action>, CompanionError> {
+    let mut actions = Vec::new();
+    let mut open: Vec<Action> = Vec::new();
+
+    for event in read_xml(path)? {
+        match event {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => match name.local_name.as_str() {
+                "Action" => open.push(Action {
+                    name: attribute(&attributes, "Name")
+                        .unwrap_or_default()
+                        .to_string(),
+                    animations: Vec::new(),
+                }),
+                "Animation" => {
+                    if let Some(action) = open.last_mut() {
+                        action.animations.push(Vec::new());
+                    }
+                }
+                "Pose" => {
+                    let Some(animation) = open.last_mut().and_then(|a| a.animations.last_mut())
+                    else {
+                        continue;
+                    };
+                    let Some(image) = attribute(&attributes, "Image") else {
+                        continue;
+                    };
+                    let velocity =
+                        match attribute(&attributes, "Velocity").and_then(|v| v.split_once(',')) {
+                            Some((x, y)) => (number(path, x)?, number(path, y)?),
+                            None => (0.0, 0.0),
+                        };
+                    let duration = match attribute(&attributes, "Duration") {
+                        Some(duration) => number(path, duration)?,
+                        None => DEFAULT_FRAME_DURATION_MS / TICK_MS,
+                    };
+                    animation.push(Pose {
+                        image: image.trim_start_matches('/').to_string(),
+                        velocity,
+                        duration,
+                    });
+                }
+                _ => {}
+            },
+            XmlEvent::EndElement { name } if name.local_name == "Action" => {
+                if let Some(action) = open.pop()
+                    && action.animations.iter().any(|poses| !poses.is_empty())
+                {
+                    actions.push(action);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if actions.is_empty() {
+        return Err(CompanionError::ImportFailed {
+            path: path.to_path_buf(),
+            reason: "no actions with poses found, only English (Shimeji-ee) files are supported"
+                .to_string(),
+        });
+    }
+    Ok(actions)
+}
+
+/// Reads the behaviors declared in `behaviors.xml`.
+pub fn read_behaviors(path: &Path) -> Result<Vec<ShimejiBehavior>, CompanionError> {
+    let mut behaviors = Vec::new();
+    let mut open: Option<ShimejiBehavior> = None;
+
+    for event in read_xml(path)? {
+        match event {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => match name.local_name.as_str() {
+                "Behavior" => {
+                    let name = attribute(&attributes, "Name").unwrap_or_default();
+                    open = Some(ShimejiBehavior {
+                        name: name.to_string(),
+                        action: attribute(&attributes, "Action").unwrap_or(name).to_string(),
+                        frequency: number(
+                            path,
+                            attribute(&attributes, "Frequency").unwrap_or("0"),
+                        )?,
+                        next: None,
+                    });
+                }
+                "NextBehaviorList" => {
+                    if let Some(behavior) = &mut open {
+                        behavior.next = Some(NextBehaviors {
+                            add: attribute(&attributes, "Add") != Some("false"),
+                            behaviors: Vec::new(),
+                        });
+                    }
+                }
+                "BehaviorReference" => {
+                    if let Some(next) = open.as_mut().and_then(|b| b.next.as_mut()) {
+                        next.behaviors.push((
+                            attribute(&attributes, "Name")
+                                .unwrap_or_default()
+                                .to_string(),
+                            number(path, attribute(&attributes, "Frequency").unwrap_or("0"))?,
+                        ));
+                    }
+                }
+                _ => {}
+            },
+            XmlEvent::EndElement { name } if name.local_name == "Behavior" => {
+                behaviors.extend(open.take());
+            }
+            _ => {}
+        }
+    }
+
+    if behaviors.is_empty() {
+        return Err(CompanionError::ImportFailed {
+            path: path.to_path_buf(),
+            reason: "no behaviors found, only English (Shimeji-ee) files are supported".to_string(),
+        });
+    }
+    Ok(behaviors)
+}
+
+/// Quotes a string as a TOML basic string.
+fn quoted(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl ShimejiImport {
+    /// Renders the companion's `companion.toml`.
+    pub fn to_toml(&self) -> String {
+        let mut toml = format!(
+            "# Imported from the Shimeji pack in {}\n\n[animations]\n",
+            self.images.display()
+        );
+
+        for (name, sprites) in &self.animations {
+            let sprites: Vec<String> = sprites
+                .iter()
+                .map(|s| {
+                    format!(
+                        "{{ path = {}, duration_ms = {} }}",
+                        quoted(&s.path),
+                        s.duration_ms
+                    )
+                })
+                .collect();
+            if let [sprite] = sprites.as_slice() {
+                let _ = writeln!(toml, "{name} = [{sprite}]");
+            } else {
+                let _ = writeln!(toml, "{name} = [\n    {},\n]", sprites.join(",\n    "));
+            }
+        }
+
+        if let Some(transitions) = &self.transitions {
+            toml.push_str("\n[transitions]\n");
+            for (state, row) in transitions {
+                let row: Vec<String> = row
+                    .iter()
+                    .map(|(behavior, weight)| format!("{behavior} = {weight}"))
+                    .collect();
+                let _ = writeln!(toml, "{state} = {{ {} }}", row.join(", "));
+            }
+        }
+
+        toml
+    }
+
+    /// Writes `companion.toml` to `output` and copies the used images next to it.
+    ///
+    /// # Arguments
+    /// * `output` - Directory of the new companion, created if missing.
+    /// * `force` - Whether an existing `companion.toml` may be overwritten.
+    pub fn write(&self, output: &Path, force: bool) -> Result<(), CompanionError> {
+        let config_path = output.join("companion.toml");
+        if config_path.exists() && !force {
+            return Err(CompanionError::ImportFailed {
+                path: config_path,
+                reason: "already exists, pass --force to overwrite it".to_string(),
+            });
+        }
+
+        let io_error = |path: &Path| {
+            let path = path.to_path_buf();
+            move |source| CompanionError::MissingFile { path, source }
+        };
+
+        fs::create_dir_all(output).map_err(io_error(output))?;
+        for sprite in self.animations.values().flatten() {
+            let target = output.join(&sprite.path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(io_error(parent))?;
+            }
+            let source = self.images.join(&sprite.path);
+            fs::copy(&source, &target).map_err(io_error(&source))?;
+        }
+        fs::write(&config_path, self.to_toml()).map_err(io_error(&config_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::companion::read_companion_config;
+
+    /// Writes `contents` to a fresh temporary file named after `name`.
+    fn write_temp(name: &str, contents: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("desktop-companion-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn behavior(name: &str, action: &str, frequency: f32) -> ShimejiBehavior {
+        ShimejiBehavior {
+            name: name.to_string(),
+            action: action.to_string(),
+            frequency,
+            next: None,
+        }
+    }
+
+    #[test]
+    fn actions_are_read_with_their_poses() {
+        let path = write_temp(
+            "actions.xml",
+            r##"<?xml version="1.0" encoding="UTF-8"?>
+<Mascot xmlns="http://www.group-finity.com/Mascot">
+  <ActionList>
+    <Action Name="Stand" Type="Stay">
+      <Animation>
+        <Pose Image="/shime1.png" ImageAnchor="64,128" Velocity="0,0" Duration="250" />
+      </Animation>
+    </Action>
+    <Action Name="Walk" Type="Move">
+      <Animation>
+        <Pose Image="/shime1.png" Velocity="-2,0" Duration="6" />
+        <Pose Image="/shime2.png" Velocity="-2,0" />
+      </Animation>
+    </Action>
+    <Action Name="Pinched" Type="Embedded">
+      <Animation Condition="#{footX &lt; mascot.environment.cursor.x-50}">
+        <Pose Image="/shime9.png" Duration="250" />
+      </Animation>
+      <Animation>
+        <Pose Image="/shime1.png" Duration="250" />
+      </Animation>
+    </Action>
+    <Action Name="SitAndLookUp" Type="Sequence">
+      <ActionReference Name="Sit" />
+    </Action>
+  </ActionList>
+</Mascot>
+"##,
+        );
+
+        let actions = read_actions(&path).unwrap();
+
+        let names: Vec<&str> = actions.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["Stand", "Walk", "Pinched"]);
+        let walk = &actions[1].animations[0];
+        assert_eq!(walk[0].image, "shime1.png");
+        assert_eq!(walk[0].velocity, (-2.0, 0.0));
+        assert_eq!(walk[0].duration, 6);
+        assert_eq!(walk[1].duration, DEFAULT_FRAME_DURATION_MS / TICK_MS);
+        assert_eq!(actions[2].animations.len(), 2);
+    }
+
+    #[test]
+    fn actions_without_poses_are_rejected() {
+        let path = write_temp(
+            "actions-empty.xml",
+            r#"<Mascot><ActionList><Action Name="Sit" Type="Sequence" /></ActionList></Mascot>"#,
+        );
+
+        assert!(matches!(
+            read_actions(&path),
+            Err(CompanionError::ImportFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn behaviors_are_read_with_their_next_behaviors() {
+        let path = write_temp(
+            "behaviors.xml",
+            r##"<?xml version="1.0" encoding="UTF-8"?>
+<Mascot xmlns="http://www.group-finity.com/Mascot">
+  <BehaviorList>
+    <Behavior Name="StandUp" Frequency="200" />
+    <Condition Condition="#{mascot.environment.floor.isOn(mascot.anchor)}">
+      <Behavior Name="SitDown" Action="Sit" Frequency="100">
+        <NextBehaviorList Add="false">
+          <BehaviorReference Name="StandUp" Frequency="1" />
+        </NextBehaviorList>
+      </Behavior>
+      <Behavior Name="WalkAlong" Action="Walk" Frequency="50">
+        <NextBehaviorList>
+          <BehaviorReference Name="SitDown" Frequency="2" />
+        </NextBehaviorList>
+      </Behavior>
+    </Condition>
+  </BehaviorList>
+</Mascot>
+"##,
+        );
+
+        let behaviors = read_behaviors(&path).unwrap();
+
+        assert_eq!(behaviors.len(), 3);
+        assert_eq!(behaviors[0].action, "StandUp");
+        assert_eq!(behaviors[0].frequency, 200.0);
+        assert!(behaviors[0].next.is_none());
+
+        let sit = behaviors[1].next.as_ref().unwrap();
+        assert_eq!(behaviors[1].action, "Sit");
+        assert!(!sit.add);
+        assert_eq!(sit.behaviors, [("StandUp".to_string(), 1.0)]);
+        assert!(behaviors[2].next.as_ref().unwrap().add);
+    }
+
+    #[test]
+    fn transitions_mix_next_behaviors_by_frequency() {
+        let mut sit = behavior("SitDown", "Sit", 100.0);
+        sit.next = Some(NextBehaviors {
+            add: false,
+            behaviors: vec![("WalkAlong".to_string(), 1.0)],
+        });
+        let behaviors = [
+            behavior("StandUp", "Stand", 100.0),
+            sit,
+            behavior("WalkAlong", "Walk", 200.0),
+            behavior("Jumping", "Jumping", 0.0),
+            behavior("ClimbWall", "ClimbWall", 100.0),
+        ];
+
+        let table = transitions(&behaviors).unwrap();
+
+        // the jump never happens and climbing has no counterpart, so neither shows up
+        let initial = BTreeMap::from([
+            (Behavior::Idle, 0.5),
+            (Behavior::WalkLeft, 0.25),
+            (Behavior::WalkRight, 0.25),
+        ]);
+        // standing up is followed by anything, sitting down only by walking
+        let idle = BTreeMap::from([
+            (Behavior::Idle, 0.25),
+            (Behavior::WalkLeft, 0.375),
+            (Behavior::WalkRight, 0.375),
+        ]);
+        assert_eq!(
+            table,
+            BTreeMap::from([("idle".to_string(), idle), ("initial".to_string(), initial)])
+        );
+    }
+
+    #[test]
+    fn transitions_need_a_supported_behavior() {
+        assert_eq!(
+            transitions(&[behavior("ClimbWall", "ClimbWall", 100.0)]),
+            None
+        );
+    }
+
+    #[test]
+    fn normalized_weights_sum_up_to_one() {
+        let weights = BTreeMap::from([
+            (Behavior::Idle, 1.0),
+            (Behavior::WalkLeft, 2.0),
+            (Behavior::WalkRight, 0.0),
+            (Behavior::Jump, 0.0001),
+        ]);
+
+        assert_eq!(
+            normalized(weights),
+            BTreeMap::from([(Behavior::Idle, 0.333), (Behavior::WalkLeft, 0.667)])
+        );
+        assert!(normalized(BTreeMap::from([(Behavior::Idle, 0.0)])).is_empty());
+    }
+
+    #[test]
+    fn animation_names() {
+        assert_eq!(animation_name("Stand"), "idle");
+        assert_eq!(animation_name("Pinched"), "drag");
+        assert_eq!(animation_name("SitAndLookUp"), "sit_and_look_up");
+        assert_eq!(animation_name("sit-down"), "sit_down");
+        assert_eq!(animation_name("Sprawl "), "sprawl");
+    }
+
+    #[test]
+    fn generated_toml_loads_back() {
+        let sprite = |path: &str, duration_ms| Sprite {
+            path: path.to_string(),
+            duration_ms,
+            rect: None,
+        };
+        let import = ShimejiImport {
+            images: PathBuf::from("/shimeji/img"),
+            animations: BTreeMap::from([
+                ("idle".to_string(), vec![sprite("shime1.png", 10000)]),
+                (
+                    "walk".to_string(),
+                    vec![sprite("shime1.png", 240), sprite("sub dir/\"2\".png", 240)],
+                ),
+            ]),
+            transitions: Some(BTreeMap::from([
+                (
+                    "idle".to_string(),
+                    BTreeMap::from([(Behavior::Idle, 0.25), (Behavior::WalkLeft, 0.375)]),
+                ),
+                (
+                    "initial".to_string(),
+                    BTreeMap::from([(Behavior::Idle, 0.5), (Behavior::Jump, 0.125)]),
+                ),
+            ])),
+            size: (128, 128),
+            walkspeed: 50.0,
+        };
+
+        let path = write_temp("companion.toml", &import.to_toml());
+        let config = read_companion_config(&path).unwrap();
+
+        let frames: Vec<(&str, u64)> = config.animations["walk"]
+            .frames
+            .iter()
+            .map(|s| (s.path.as_str(), s.duration_ms))
+            .collect();
+        assert_eq!(frames, [("shime1.png", 240), ("sub dir/\"2\".png", 240)]);
+        assert_eq!(config.animations["idle"].frames[0].duration_ms, 10000);
+        assert_eq!(
+            config.transitions.weights_for(Some(Behavior::Idle)),
+            [(Behavior::Idle, 0.25), (Behavior::WalkLeft, 0.375)]
+        );
+        assert_eq!(
+            config.transitions.weights_for(None),
+            [(Behavior::Idle, 0.5), (Behavior::Jump, 0.125)]
+        );
+    }
+}
@@ -0,0 +1,416 @@
+//! Module `simulate` runs a companion without a window, as fast as the CPU allows.
+//!
+//! The companion lives in a [`VirtualWindow`] and its time comes from a [`ManualClock`] advanced
+//! in fixed steps, so a day of behavior takes seconds. The result is a timeline of the behaviors
+//! picked along with statistics for tuning the transition weights:
+//! - time spent per behavior
+//! - how much of the screen the companion covered
+//! - how often it ran into the left and right edges
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::{
+    behavior::Behavior,
+    clock::ManualClock,
+    companion::{CompanionConfig, CompanionPack},
+    core::CompanionApp,
+    errors::CompanionError,
+    window::VirtualWindow,
+};
+
+/// Distance in pixels from an edge at which the companion counts as touching it.
+const EDGE_TOLERANCE: f32 = 0.5;
+
+/// Parameters of a simulation.
+#[derive(Debug, Clone, Copy)]
+pub struct Settings {
+    /// How much time to simulate.
+    pub duration: Duration,
+
+    /// Time between two updates of the companion.
+    pub step: Duration,
+
+    /// Size of the simulated screen in pixels.
+    pub screen: (f32, f32),
+}
+
+/// A behavior picked during the simulation.
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    /// Seconds since the start of the simulation.
+    pub time: f64,
+
+    /// Name of the behavior.
+    pub behavior: &'static str,
+
+    /// Horizontal position of the companion when the behavior started.
+    pub x: f32,
+
+    /// Vertical position of the companion when the behavior started.
+    pub y: f32,
+
+    /// Seconds the behavior kept the companion busy. The companion idles from then on until the
+    /// next behavior is picked.
+    pub duration: f64,
+}
+
+/// Time spent in a single behavior.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BehaviorStats {
+    /// How often the behavior was picked.
+    pub count: usize,
+
+    /// Seconds spent in the behavior. For `idle` this includes the waits between behaviors.
+    pub seconds: f64,
+
+    /// Fraction of the simulated time spent in the behavior.
+    pub share: f64,
+}
+
+/// Statistics of a whole simulation.
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    /// Seed of the companion's RNG.
+    pub seed: u64,
+
+    /// Simulated seconds.
+    pub seconds: f64,
+
+    /// Statistics per behavior name.
+    pub behaviors: BTreeMap<&'static str, BehaviorStats>,
+
+    /// Fraction of the screen, split into cells of the companion's size, the companion's center
+    /// visited.
+    pub coverage: f64,
+
+    /// Fraction of the screen's columns of the companion's width the companion's center visited.
+    pub horizontal_coverage: f64,
+
+    /// How often the companion arrived at the left edge of the screen.
+    pub left_edge_hits: usize,
+
+    /// How often the companion arrived at the right edge of the screen.
+    pub right_edge_hits: usize,
+}
+
+/// Result of [`simulate`].
+#[derive(Debug, Clone, Serialize)]
+pub struct Simulation {
+    /// Behaviors in the order they were picked.
+    pub timeline: Vec<Entry>,
+
+    /// Statistics over the whole simulation.
+    pub summary: Summary,
+}
+
+/// Format of the timeline written by [`Simulation::write`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// One line per behavior, the summary isn't included.
+    Csv,
+    /// The timeline and the summary as a single object.
+    Json,
+}
+
+impl Format {
+    /// Guesses the format from the extension of `path`, CSV unless it ends in `.json`.
+    pub fn of(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Format::Json,
+            _ => Format::Csv,
+        }
+    }
+}
+
+/// Grid of cells of the companion's size over the screen, remembering which ones were visited.
+struct Coverage {
+    cell: (f32, f32),
+    cells: (usize, usize),
+    visited: HashSet<(usize, usize)>,
+}
+
+impl Coverage {
+    fn new(screen: (f32, f32), companion: (f32, f32)) -> Self {
+        let cell = (companion.0.max(1.0), companion.1.max(1.0));
+        Self {
+            cell,
+            cells: (
+                (screen.0 / cell.0).ceil().max(1.0) as usize,
+                (screen.1 / cell.1).ceil().max(1.0) as usize,
+            ),
+            visited: HashSet::new(),
+        }
+    }
+
+    /// Marks the cell containing `point`, ignoring points off the screen.
+    fn visit(&mut self, point: (f32, f32)) {
+        if point.0 < 0.0 || point.1 < 0.0 {
+            return;
+        }
+        let cell = (
+            (point.0 / self.cell.0) as usize,
+            (point.1 / self.cell.1) as usize,
+        );
+        if cell.0 < self.cells.0 && cell.1 < self.cells.1 {
+            self.visited.insert(cell);
+        }
+    }
+
+    /// Fraction of all cells visited.
+    fn total(&self) -> f64 {
+        self.visited.len() as f64 / (self.cells.0 * self.cells.1) as f64
+    }
+
+    /// Fraction of columns with at least one visited cell.
+    fn horizontal(&self) -> f64 {
+        let columns: HashSet<usize> = self.visited.iter().map(|cell| cell.0).collect();
+        columns.len() as f64 / self.cells.0 as f64
+    }
+}
+
+/// Simulates a companion on a virtual screen, recording every behavior it picks.
+///
+/// The companion isn't dragged or thrown, so it only does what its behaviors make it do.
+///
+/// # Arguments
+/// * `pack` - Pack of the companion.
+/// * `companion_config` - Configuration loaded from the pack.
+/// * `seed` - Seed for the companion's RNG, the same seed gives the same timeline.
+/// * `settings` - Length, resolution and screen size of the simulation.
+pub fn simulate(
+    pack: &CompanionPack,
+    companion_config: CompanionConfig,
+    seed: u64,
+    settings: Settings,
+) -> Result<Simulation, CompanionError> {
+    let clock = Arc::new(ManualClock::new());
+    let mut app =
+        CompanionApp::headless(pack, companion_config, seed, settings.screen, clock.clone())?;
+    let mut window = VirtualWindow::new(settings.screen);
+
+    let size = (pack.companion.width, pack.companion.height);
+    let max_x = (settings.screen.0 - size.0).max(0.0);
+    let mut coverage = Coverage::new(settings.screen, size);
+    let (mut left_edge_hits, mut right_edge_hits) = (0, 0);
+    let (mut at_left, mut at_right) = (false, false);
+
+    let mut timeline: Vec<Entry> = Vec::new();
+    // index of the entry whose behavior is still playing
+    let mut busy: Option<usize> = None;
+    let mut elapsed = Duration::ZERO;
+    while elapsed < settings.duration {
+        clock.advance(settings.step);
+        elapsed += settings.step;
+
+        let start = window.position;
+        let started = app
+            .step(&mut window)
+            .expect("Failed to update the simulated companion");
+        if let Some(behavior) = started {
+            timeline.push(Entry {
+                time: elapsed.as_secs_f64(),
+                behavior: behavior.name(),
+                x: start.0,
+                y: start.1,
+                duration: 0.0,
+            });
+            busy = Some(timeline.len() - 1);
+        }
+        if let Some(index) = busy
+            && !app.animations.is_busy()
+        {
+            let entry = &mut timeline[index];
+            entry.duration = elapsed.as_secs_f64() - entry.time;
+            busy = None;
+        }
+
+        let (x, y) = window.position;
+        coverage.visit((x + size.0 / 2.0, y + size.1 / 2.0));
+        let (left, right) = (x <= EDGE_TOLERANCE, x >= max_x - EDGE_TOLERANCE);
+        left_edge_hits += usize::from(left && !at_left);
+        right_edge_hits += usize::from(right && !at_right);
+        (at_left, at_right) = (left, right);
+    }
+
+    let seconds = elapsed.as_secs_f64();
+    if let Some(index) = busy {
+        let entry = &mut timeline[index];
+        entry.duration = seconds - entry.time;
+    }
+
+    // the time between the end of a behavior and the next pick is spent idling
+    let next_picks: Vec<f64> = timeline
+        .iter()
+        .skip(1)
+        .map(|entry| entry.time)
+        .chain([seconds])
+        .collect();
+    let mut behaviors: BTreeMap<&'static str, BehaviorStats> = BTreeMap::new();
+    for (entry, next_pick) in timeline.iter().zip(next_picks) {
+        let stats = behaviors.entry(entry.behavior).or_default();
+        stats.count += 1;
+        stats.seconds += entry.duration;
+
+        let idle = next_pick - entry.time - entry.duration;
+        if idle > 0.0 {
+            behaviors.entry(Behavior::Idle.name()).or_default().seconds += idle;
+        }
+    }
+    for stats in behaviors.values_mut() {
+        stats.share = stats.seconds / seconds;
+    }
+
+    Ok(Simulation {
+        timeline,
+        summary: Summary {
+            seed,
+            seconds,
+            behaviors,
+            coverage: coverage.total(),
+            horizontal_coverage: coverage.horizontal(),
+            left_edge_hits,
+            right_edge_hits,
+        },
+    })
+}
+
+impl Simulation {
+    /// Writes the simulation to `path` in `format`.
+    pub fn write(&self, path: &Path, format: Format) -> Result<(), CompanionError> {
+        let write = || -> io::Result<()> {
+            let mut out = BufWriter::new(File::create(path)?);
+            match format {
+                Format::Csv => {
+                    writeln!(out, "time,behavior,x,y,duration")?;
+                    for entry in &self.timeline {
+                        writeln!(
+                            out,
+                            "{:.3},{},{:.1},{:.1},{:.3}",
+                            entry.time, entry.behavior, entry.x, entry.y, entry.duration
+                        )?;
+                    }
+                }
+                Format::Json => {
+                    serde_json::to_writer_pretty(&mut out, self)?;
+                    writeln!(out)?;
+                }
+            }
+            out.flush()
+        };
+
+        write().map_err(|source| CompanionError::WriteFailed {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Simulated {} with seed {}",
+            humantime::format_duration(Duration::from_secs(self.seconds as u64)),
+            self.seed
+        )?;
+        writeln!(
+            f,
+            "{:<12}{:>8}{:>12}{:>8}",
+            "behavior", "count", "seconds", "share"
+        )?;
+        for (name, stats) in &self.behaviors {
+            writeln!(
+                f,
+                "{name:<12}{:>8}{:>12.1}{:>7.1}%",
+                stats.count,
+                stats.seconds,
+                stats.share * 100.0
+            )?;
+        }
+        writeln!(
+            f,
+            "coverage: {:.1}% of the screen, {:.1}% of its width",
+            self.coverage * 100.0,
+            self.horizontal_coverage * 100.0
+        )?;
+        write!(
+            f,
+            "edge hits: {} left, {} right",
+            self.left_edge_hits, self.right_edge_hits
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::companion::tests::write_pack;
+
+    #[test]
+    fn same_seed_gives_same_timeline() {
+        // with this many fidgets, an unordered map would iterate them differently in each run
+        let fidgets = ["blink", "yawn", "stretch", "scratch", "sniff", "wave"];
+        let mut config = String::from(
+            "[animations]\nidle = [{ path = \"idle1.png\" }]\nwalk = [{ path = \"walk1.png\" }]\n",
+        );
+        for name in fidgets {
+            config += &format!("{name} = [{{ path = \"{name}.png\" }}]\n");
+        }
+        config += "\n[fidgets]\n";
+        for (i, name) in fidgets.iter().enumerate() {
+            config += &format!("{name} = {{ every = {}.0, jitter = 0.5 }}\n", i + 1);
+        }
+        let pack = write_pack("fidgets", &config);
+        let settings = Settings {
+            duration: Duration::from_secs(600),
+            step: Duration::from_millis(40),
+            screen: (1920.0, 1080.0),
+        };
+        let run = || {
+            let config = pack.load_config().unwrap();
+            let simulation = simulate(&pack, config, 42, settings).unwrap();
+            simulation
+                .timeline
+                .iter()
+                .map(|entry| (entry.time, entry.behavior, entry.x, entry.y))
+                .collect::<Vec<_>>()
+        };
+
+        let first = run();
+        assert!(first.len() > 10);
+        assert_eq!(first, run());
+    }
+
+    #[test]
+    fn screen_narrower_than_companion() {
+        let pack = write_pack(
+            "narrow",
+            r#"
+[animations]
+idle = [{ path = "idle1.png" }]
+walk = [{ path = "walk1.png" }]
+
+[transitions]
+initial = { walk_left = 0.3, walk_right = 0.3, jump = 0.4 }
+"#,
+        );
+        let settings = Settings {
+            duration: Duration::from_secs(120),
+            step: Duration::from_millis(40),
+            screen: (50.0, 50.0),
+        };
+
+        let simulation = simulate(&pack, pack.load_config().unwrap(), 3, settings).unwrap();
+        assert!(simulation.timeline.iter().all(|entry| entry.x == 0.0));
+    }
+}
@@ -0,0 +1,254 @@
+//! Module `supervisor` spawns one process per configured companion and keeps them alive.
+//!
+//! `ggez` owns the event loop of the thread it runs on and never returns from it, so a single
+//! process can only show one companion. The supervisor works around that by re-launching the
+//! current executable with `run --companion <name>` for every companion in `config.toml`.
+//!
+//! It handles:
+//! - Restarting crashed children with exponential backoff
+//! - Leaving children that exited cleanly (e.g. the user closed the window) alone
+//! - Giving up on children that failed because of their configuration, which a restart can't fix
+//! - Terminating all children when the supervisor receives `SIGTERM` or `SIGINT`
+
+use std::{
+    env, io,
+    process::{Child, Command},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{error, info, warn};
+
+use crate::{companion::AppConfig, errors::CompanionError};
+
+/// How often children are polled for exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Delay before the first restart of a crashed child.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Upper limit of the restart delay.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A child that stays up this long is considered healthy again and its backoff is reset.
+const HEALTHY_UPTIME: Duration = Duration::from_secs(30);
+
+/// How long children get to exit after being asked to terminate before they are killed.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+
+/// Set by the signal handler once the supervisor has been asked to stop.
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+/// State of a single supervised companion process.
+struct Supervised {
+    /// Name of the companion passed to `run --companion`.
+    name: String,
+    /// Command-line arguments of the child process.
+    args: Vec<String>,
+    /// Running child process, if any.
+    child: Option<Child>,
+    /// When the current child was started.
+    started: Instant,
+    /// Delay before the next restart.
+    backoff: Duration,
+    /// When the child should be restarted after a crash.
+    restart_at: Option<Instant>,
+}
+
+impl Supervised {
+    fn new(name: String, args: Vec<String>) -> Self {
+        Self {
+            name,
+            args,
+            child: None,
+            started: Instant::now(),
+            backoff: INITIAL_BACKOFF,
+            restart_at: None,
+        }
+    }
+
+    /// Spawns the child process for this companion.
+    fn spawn(&mut self) -> io::Result<()> {
+        let child = Command::new(env::current_exe()?).args(&self.args).spawn()?;
+
+        info!("Started companion {} (pid {})", self.name, child.id());
+        self.child = Some(child);
+        self.started = Instant::now();
+        self.restart_at = None;
+        Ok(())
+    }
+
+    /// Checks whether the child exited and schedules a restart if it crashed. Children exiting
+    /// with one of the [`CompanionError::EXIT_CODES`] aren't restarted, as they would fail again.
+    ///
+    /// # Returns
+    /// `true` while the companion is still running or waiting to be restarted.
+    fn poll(&mut self) -> io::Result<bool> {
+        if let Some(restart_at) = self.restart_at {
+            if Instant::now() >= restart_at {
+                // retry later rather than on every poll if spawning keeps failing
+                self.restart_at = Some(Instant::now() + self.backoff);
+                self.spawn()?;
+            }
+            return Ok(true);
+        }
+
+        let Some(child) = &mut self.child else {
+            return Ok(false);
+        };
+        let Some(status) = child.try_wait()? else {
+            return Ok(true);
+        };
+        self.child = None;
+
+        if status.success() {
+            info!("Companion {} exited", self.name);
+            return Ok(false);
+        }
+        if let Some(code) = status.code()
+            && CompanionError::EXIT_CODES.contains(&code)
+        {
+            error!(
+                "Companion {} failed because of its configuration ({status}), not restarting it",
+                self.name
+            );
+            return Ok(false);
+        }
+
+        if self.started.elapsed() >= HEALTHY_UPTIME {
+            self.backoff = INITIAL_BACKOFF;
+        }
+        warn!(
+            "Companion {} crashed ({status}), restarting in {}",
+            self.name,
+            humantime::format_duration(self.backoff)
+        );
+        self.restart_at = Some(Instant::now() + self.backoff);
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+        Ok(true)
+    }
+
+    /// Asks the child to terminate.
+    fn terminate(&mut self) {
+        self.restart_at = None;
+        if let Some(child) = &mut self.child {
+            #[cfg(unix)]
+            // SAFETY: `kill` has no memory safety requirements, the pid belongs to our child
+            unsafe {
+                libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+            }
+            #[cfg(not(unix))]
+            let _ = child.kill();
+        }
+    }
+
+    /// Waits for the child to exit until `deadline`, killing it afterwards.
+    fn reap(&mut self, deadline: Instant) {
+        let Some(mut child) = self.child.take() else {
+            return;
+        };
+
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = child.try_wait() {
+                return;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        warn!("Companion {} didn't exit in time, killing it", self.name);
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn request_shutdown(_signal: libc::c_int) {
+    SHUTDOWN.store(true, Ordering::SeqCst);
+}
+
+/// Installs handlers setting [`SHUTDOWN`] on `SIGTERM` and `SIGINT`.
+fn install_signal_handlers() {
+    #[cfg(unix)]
+    for signal in [libc::SIGTERM, libc::SIGINT] {
+        // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+        unsafe {
+            libc::signal(signal, request_shutdown as *const () as libc::sighandler_t);
+        }
+    }
+}
+
+/// Runs every companion from `config` in its own process until all of them exit or the
+/// supervisor is asked to stop.
+///
+/// # Arguments
+/// * `config` - Application configuration listing the companions.
+/// * `global_args` - Global options forwarded to every child, such as `--config`.
+/// * `seed` - Base RNG seed. Each child gets its own seed derived from it so companions don't
+///   move in lockstep.
+///
+/// # Returns
+/// * `Ok(())` once all children exited.
+/// * `Err(io::Error)` if a child process could not be spawned at startup. The children started
+///   before it are terminated first.
+pub fn supervise(config: &AppConfig, global_args: &[String], seed: Option<u64>) -> io::Result<()> {
+    install_signal_handlers();
+
+    let mut children: Vec<Supervised> = config
+        .companion
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let mut args = global_args.to_vec();
+            args.extend(["run".to_string(), "--companion".to_string(), c.name.clone()]);
+            if let Some(seed) = seed {
+                args.extend([
+                    "--seed".to_string(),
+                    seed.wrapping_add(i as u64).to_string(),
+                ]);
+            }
+            Supervised::new(c.name.clone(), args)
+        })
+        .collect();
+
+    for i in 0..children.len() {
+        if let Err(e) = children[i].spawn() {
+            error!("Failed to start companion {}: {e}", children[i].name);
+            // don't leave the companions started so far running without a supervisor
+            shutdown(&mut children[..i]);
+            return Err(e);
+        }
+    }
+
+    while !SHUTDOWN.load(Ordering::SeqCst) {
+        let mut alive = false;
+        for child in &mut children {
+            match child.poll() {
+                Ok(running) => alive |= running,
+                Err(e) => error!("Failed to supervise companion {}: {e}", child.name),
+            }
+        }
+
+        if !alive {
+            info!("All companions exited");
+            return Ok(());
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    info!("Shutting down companions");
+    shutdown(&mut children);
+    Ok(())
+}
+
+/// Terminates `children` and waits for them to exit, killing those that take longer than
+/// [`SHUTDOWN_GRACE`].
+fn shutdown(children: &mut [Supervised]) {
+    for child in children.iter_mut() {
+        child.terminate();
+    }
+    let deadline = Instant::now() + SHUTDOWN_GRACE;
+    for child in children.iter_mut() {
+        child.reap(deadline);
+    }
+}
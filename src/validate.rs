@@ -0,0 +1,458 @@
+//! Module `validate` lints the configuration and companion packs without opening a window.
+//!
+//! Unlike loading a companion, which stops at the first error, validation collects every problem
+//! it finds so all of them can be fixed in one go. It checks that:
+//! - `config.toml` and every `companion.toml` deserialize
+//! - every row of a transition matrix only has known behaviors with positive weights, and the
+//!   `initial` row is present
+//! - companion names are unique, their sizes are positive and their physics let them come to
+//!   rest
+//! - the [`REQUIRED_ANIMATIONS`] are present
+//! - every sprite exists, decodes, and matches the companion's `width` and `height`
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use config::Value;
+
+use crate::{
+    behavior::{Behavior, INITIAL_STATE, TransitionTable},
+    companion::{
+        AppConfig, CompanionConfig, CompanionPack, REQUIRED_ANIMATIONS, StepConfig,
+        parse_companion_config, read_companion_table, split_transition_key,
+    },
+    errors::CompanionError,
+};
+
+/// A single problem found during validation.
+#[derive(Debug)]
+pub struct Problem {
+    /// File the problem was found in.
+    pub file: PathBuf,
+    /// 1-based line in `file` the problem refers to, if known.
+    pub line: Option<usize>,
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{line}: {}", self.file.display(), self.message),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+/// Source of a configuration file, used to point problems at the line they come from.
+struct Source {
+    path: PathBuf,
+    text: String,
+}
+
+impl Source {
+    fn read(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            text: fs::read_to_string(path).unwrap_or_default(),
+        }
+    }
+
+    /// Returns the first line containing `needle` as a quoted string.
+    fn line_of(&self, needle: &str) -> Option<usize> {
+        let quoted = [format!("\"{needle}\""), format!("'{needle}'")];
+        self.text
+            .lines()
+            .position(|line| quoted.iter().any(|q| line.contains(q.as_str())))
+            .map(|index| index + 1)
+    }
+
+    /// Returns the line of the header of the table `[table]`.
+    fn line_of_table(&self, table: &str) -> Option<usize> {
+        let header = format!("[{table}]");
+        self.text
+            .lines()
+            .position(|line| line.trim() == header)
+            .map(|index| index + 1)
+    }
+
+    /// Returns the line declaring `key` inside the table `[table]`.
+    fn line_of_key(&self, table: &str, key: &str) -> Option<usize> {
+        let header = format!("[{table}]");
+        self.text
+            .lines()
+            .enumerate()
+            .skip_while(|(_, line)| line.trim() != header)
+            .skip(1)
+            .take_while(|(_, line)| !line.trim_start().starts_with('['))
+            .find(|(_, line)| {
+                line.trim_start()
+                    .strip_prefix(key)
+                    .is_some_and(|rest| rest.trim_start().starts_with('='))
+            })
+            .map(|(index, _)| index + 1)
+    }
+
+    fn problem(&self, line: Option<usize>, message: impl Into<String>) -> Problem {
+        Problem {
+            file: self.path.clone(),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+/// Validates `config.toml` and every companion pack it references.
+///
+/// # Arguments
+/// * `config_path` - Path to `config.toml`.
+/// * `config` - Configuration loaded from `config_path`.
+///
+/// # Returns
+/// All problems found, empty if everything is fine.
+pub fn validate_config(config_path: &Path, config: &AppConfig) -> Vec<Problem> {
+    let source = Source::read(config_path);
+    let mut problems = Vec::new();
+
+    let mut names = HashSet::new();
+    for c in &config.companion {
+        let line = source.line_of(&c.name);
+        if !names.insert(&c.name) {
+            problems.push(source.problem(line, format!("duplicate companion name `{}`", c.name)));
+        }
+        if c.width <= 0.0 || c.height <= 0.0 {
+            problems.push(source.problem(
+                line,
+                format!(
+                    "companion `{}` has invalid size {}x{}, both must be positive",
+                    c.name, c.width, c.height
+                ),
+            ));
+        }
+        if let Err(e) = c.physics.check() {
+            problems.push(source.problem(line, format!("companion `{}`: {e}", c.name)));
+        }
+    }
+
+    for pack in config.packs(config_path) {
+        problems.extend(validate_pack(&pack));
+    }
+
+    problems
+}
+
+/// Validates a single companion pack.
+///
+/// # Returns
+/// All problems found in the pack's `companion.toml` and sprites.
+pub fn validate_pack(pack: &CompanionPack) -> Vec<Problem> {
+    let config_path = pack.config_path();
+    let source = Source::read(&config_path);
+
+    let mut table = match read_companion_table(&config_path) {
+        Ok(table) => table,
+        Err(e) => return vec![load_problem(&source, e)],
+    };
+
+    // checked row by row, so a single bad weight doesn't hide every other problem
+    let mut problems = validate_transitions(&source, table.remove("transitions"));
+    let config = match parse_companion_config(&config_path, table) {
+        Ok(config) => config,
+        // nothing else can be checked without a parsed configuration
+        Err(e) => {
+            problems.push(load_problem(&source, e));
+            return problems;
+        }
+    };
+
+    for animation in config.missing_animations() {
+        problems.push(source.problem(
+            source.line_of_key("animations", animation),
+            format!(
+                "required animation `{animation}` is missing (required: {})",
+                REQUIRED_ANIMATIONS.join(", ")
+            ),
+        ));
+    }
+    let mut fidgets: Vec<_> = config.fidgets.iter().collect();
+    fidgets.sort_by_key(|(name, _)| *name);
+    for (name, fidget) in fidgets {
+        if !config.animations.contains_key(name) {
+            problems.push(source.problem(
+                source.line_of_key("fidgets", name),
+                format!("fidget `{name}` refers to an animation that doesn't exist"),
+            ));
+        }
+        if fidget.every <= 0.0 || fidget.jitter < 0.0 {
+            problems.push(source.problem(
+                source.line_of_key("fidgets", name),
+                format!("fidget `{name}` needs a positive `every` and a non-negative `jitter`"),
+            ));
+        }
+    }
+    let mut sequences: Vec<_> = config.sequences.iter().collect();
+    sequences.sort_by_key(|(behavior, _)| **behavior);
+    for (behavior, steps) in sequences {
+        for animation in steps.iter().flat_map(StepConfig::animations) {
+            if !config.animations.contains_key(animation) {
+                problems.push(source.problem(
+                    source.line_of_key("sequences", behavior.name()),
+                    format!(
+                        "sequence `{behavior}` plays `{animation}`, an animation that doesn't exist"
+                    ),
+                ));
+            }
+        }
+    }
+    let mut transitions: Vec<_> = config.animation_transitions.iter().collect();
+    transitions.sort();
+    for (key, name) in transitions {
+        let line = source.line_of(key);
+        // `from` and `to` may also name animations played with idle frames, like `jump`
+        if split_transition_key(key).is_none() {
+            problems.push(source.problem(
+                line,
+                format!("transition `{key}` should look like `from->to`"),
+            ));
+        }
+        if !config.animations.contains_key(name) {
+            problems.push(source.problem(
+                line,
+                format!("transition `{key}` plays `{name}`, an animation that doesn't exist"),
+            ));
+        }
+    }
+    problems.extend(validate_sprites(pack, &config, &source));
+
+    problems
+}
+
+/// Turns an error loading `companion.toml` into a problem, pointing at the Aseprite export for
+/// broken exports.
+fn load_problem(source: &Source, error: CompanionError) -> Problem {
+    match error {
+        CompanionError::BadConfig { source: e, .. } => source.problem(None, e.to_string()),
+        CompanionError::BadAseprite { path, reason } => Problem {
+            file: path,
+            line: None,
+            message: reason,
+        },
+        e => source.problem(None, e.to_string()),
+    }
+}
+
+/// Checks every row of `[transitions]` on its own, the way [`TransitionTable`] checks them when
+/// loading a companion.
+///
+/// # Arguments
+/// * `source` - Source of `companion.toml`.
+/// * `transitions` - The `[transitions]` table, `None` for companions using the built-in one.
+fn validate_transitions(source: &Source, transitions: Option<Value>) -> Vec<Problem> {
+    let Some(transitions) = transitions else {
+        return Vec::new();
+    };
+    let header = source.line_of_table("transitions");
+    let rows: BTreeMap<String, BTreeMap<String, f32>> = match transitions.try_deserialize() {
+        Ok(rows) => rows,
+        Err(e) => return vec![source.problem(header, format!("invalid transitions: {e}"))],
+    };
+
+    let mut problems = Vec::new();
+    for (state, weights) in &rows {
+        let checked = weights
+            .iter()
+            .map(|(name, weight)| {
+                let behavior = name.parse::<Behavior>().map_err(|_| {
+                    format!("transition row `{state}` refers to unknown behavior `{name}`")
+                })?;
+                Ok((behavior, *weight))
+            })
+            .collect::<Result<BTreeMap<_, _>, String>>()
+            .and_then(|weights| TransitionTable::check_row(state, &weights));
+        if let Err(message) = checked {
+            problems.push(source.problem(source.line_of_key("transitions", state), message));
+        }
+    }
+    if !rows.contains_key(INITIAL_STATE) {
+        problems.push(source.problem(
+            header,
+            format!("transitions are missing the `{INITIAL_STATE}` row"),
+        ));
+    }
+
+    problems
+}
+
+/// Checks that every sprite of `config` exists, decodes, and has the companion's size.
+///
+/// Sprite sheet regions must additionally lie inside their sheet. Each file is only decoded
+/// once, and problems with reading or decoding it are reported once.
+fn validate_sprites(
+    pack: &CompanionPack,
+    config: &CompanionConfig,
+    source: &Source,
+) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let expected = (pack.companion.width as u32, pack.companion.height as u32);
+    let mut sizes: HashMap<PathBuf, Option<(u32, u32)>> = HashMap::new();
+
+    let mut animations: Vec<_> = config.animations.iter().collect();
+    animations.sort_by_key(|(name, _)| *name);
+
+    for (name, animation) in animations {
+        for (index, sprite) in animation.frames.iter().enumerate() {
+            // sheet frames all share the sheet's path, so point at the animation instead
+            let line = match sprite.rect {
+                Some(_) => source.line_of_key("animations", name),
+                None => source.line_of(&sprite.path),
+            };
+            let path = pack.asset_path(&sprite.path);
+
+            let size =
+                *sizes
+                    .entry(path.clone())
+                    .or_insert_with(|| match image_size(&path) {
+                        Ok(size) => Some(size),
+                        Err(e) => {
+                            problems.push(source.problem(
+                                line,
+                                format!("sprite {} of `{name}` {e}", path.display()),
+                            ));
+                            None
+                        }
+                    });
+            let Some((width, height)) = size else {
+                continue;
+            };
+
+            let frame = match sprite.rect {
+                Some(rect) if rect.x + rect.w > width || rect.y + rect.h > height => {
+                    problems.push(source.problem(
+                        line,
+                        format!(
+                            "frame {index} of `{name}` lies outside of {} ({width}x{height})",
+                            path.display()
+                        ),
+                    ));
+                    continue;
+                }
+                Some(rect) => (rect.w, rect.h),
+                None => (width, height),
+            };
+
+            if frame != expected {
+                problems.push(source.problem(
+                    line,
+                    format!(
+                        "frame {index} of `{name}` ({}) is {}x{}, expected {}x{}",
+                        path.display(),
+                        frame.0,
+                        frame.1,
+                        expected.0,
+                        expected.1
+                    ),
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+/// Reads and decodes the image at `path`, returning its size.
+fn image_size(path: &Path) -> Result<(u32, u32), String> {
+    let bytes = fs::read(path).map_err(|e| format!("can't be read: {e}"))?;
+    let image = image::load_from_memory(&bytes).map_err(|e| format!("can't be decoded: {e}"))?;
+    Ok((image.width(), image.height()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::companion::tests::write_pack;
+
+    /// Writes a pack whose sprites are blank images of the companion's size.
+    fn pack_with_images(name: &str, config: &str) -> CompanionPack {
+        let pack = write_pack(name, config);
+        let size = (pack.companion.width as u32, pack.companion.height as u32);
+        for sprite in config.split('"').filter(|part| part.ends_with(".png")) {
+            image::RgbaImage::new(size.0, size.1)
+                .save(pack.asset_path(sprite))
+                .unwrap();
+        }
+        pack
+    }
+
+    fn lines(problems: &[Problem]) -> Vec<Option<usize>> {
+        problems.iter().map(|problem| problem.line).collect()
+    }
+
+    #[test]
+    fn valid_pack_has_no_problems() {
+        let pack = pack_with_images(
+            "validate-ok",
+            r#"
+[animations]
+idle = [{ path = "idle1.png" }]
+walk = [{ path = "walk1.png" }]
+
+[transitions]
+initial = { idle = 1.0, walk_left = 0.5 }
+"#,
+        );
+
+        assert!(validate_pack(&pack).is_empty());
+    }
+
+    #[test]
+    fn bad_transition_doesnt_hide_other_problems() {
+        let pack = pack_with_images(
+            "validate-many",
+            r#"[animations]
+idle = [{ path = "idle1.png" }]
+walk = [{ path = "walk1.png" }]
+
+[transitions]
+initial = { idle = -1.0 }
+idle = { dance = 1.0 }
+walk_left = { idle = inf }
+"#,
+        );
+        fs::remove_file(pack.asset_path("walk1.png")).unwrap();
+
+        let problems = validate_pack(&pack);
+
+        // rows are checked in the order of their names
+        assert_eq!(lines(&problems), [Some(7), Some(6), Some(8), Some(3)]);
+        assert!(problems[0].message.contains("unknown behavior `dance`"));
+        assert!(problems[1].message.contains("has weight -1"));
+        assert!(problems[2].message.contains("weights must be positive"));
+        assert!(
+            problems[3]
+                .message
+                .contains("walk1.png of `walk` can't be read")
+        );
+    }
+
+    #[test]
+    fn missing_initial_row_points_at_the_table() {
+        let pack = pack_with_images(
+            "validate-initial",
+            r#"[animations]
+idle = [{ path = "idle1.png" }]
+walk = [{ path = "walk1.png" }]
+
+[transitions]
+idle = { walk_left = 1.0 }
+"#,
+        );
+
+        let problems = validate_pack(&pack);
+
+        assert_eq!(lines(&problems), [Some(5)]);
+        assert!(problems[0].message.contains("missing the `initial` row"));
+    }
+}
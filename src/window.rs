@@ -0,0 +1,118 @@
+//! Module `window` abstracts the companion's window, so the update loop can run without a display.
+//!
+//! Animations and the app only move the companion through a [`WindowBackend`]. [`GgezWindow`]
+//! drives the real window, while [`VirtualWindow`] keeps everything in memory.
+
+use ggez::{Context, winit::dpi::PhysicalPosition};
+
+/// Window of a companion on its monitor. Positions and sizes are in physical pixels.
+pub trait WindowBackend {
+    /// Returns the position of the window's top left corner, or `None` if it isn't known.
+    fn position(&self) -> Option<(f32, f32)>;
+
+    /// Moves the window's top left corner to `pos`.
+    fn set_position(&mut self, pos: (f32, f32));
+
+    /// Returns the size of the monitor the window is on.
+    fn monitor_size(&self) -> (f32, f32);
+
+    /// Returns the position of the mouse cursor on the monitor, or `None` if it isn't known.
+    fn cursor_position(&self) -> Option<(f32, f32)>;
+
+    /// Returns `true` once the window is shown.
+    fn is_visible(&self) -> bool;
+}
+
+/// The real window of a ggez [`Context`].
+pub struct GgezWindow<'a> {
+    ctx: &'a Context,
+}
+
+impl<'a> GgezWindow<'a> {
+    /// Wraps the window of `ctx`.
+    pub fn new(ctx: &'a Context) -> Self {
+        Self { ctx }
+    }
+}
+
+impl WindowBackend for GgezWindow<'_> {
+    fn position(&self) -> Option<(f32, f32)> {
+        let pos = self.ctx.gfx.window().outer_position().ok()?;
+        Some((pos.x as f32, pos.y as f32))
+    }
+
+    fn set_position(&mut self, pos: (f32, f32)) {
+        self.ctx
+            .gfx
+            .window()
+            .set_outer_position(PhysicalPosition::new(pos.0, pos.1));
+    }
+
+    fn monitor_size(&self) -> (f32, f32) {
+        let size = self
+            .ctx
+            .gfx
+            .window()
+            .current_monitor()
+            .expect("Failed to get current monitor")
+            .size();
+        (size.width as f32, size.height as f32)
+    }
+
+    fn cursor_position(&self) -> Option<(f32, f32)> {
+        // ggez reports the cursor relative to the window
+        let (x, y) = self.position()?;
+        let cursor = self.ctx.mouse.position();
+        Some((x + cursor.x, y + cursor.y))
+    }
+
+    fn is_visible(&self) -> bool {
+        self.ctx.gfx.window().is_visible() == Some(true)
+    }
+}
+
+/// Window that only exists in memory, on a monitor of a given size.
+#[derive(Debug, Clone)]
+pub struct VirtualWindow {
+    /// Position of the window's top left corner.
+    pub position: (f32, f32),
+
+    /// Size of the monitor.
+    pub monitor_size: (f32, f32),
+
+    /// Position of the mouse cursor, `None` if there is none.
+    pub cursor: Option<(f32, f32)>,
+}
+
+impl VirtualWindow {
+    /// Creates a window in the top left corner of a monitor of `monitor_size`.
+    pub fn new(monitor_size: (f32, f32)) -> Self {
+        Self {
+            position: (0.0, 0.0),
+            monitor_size,
+            cursor: None,
+        }
+    }
+}
+
+impl WindowBackend for VirtualWindow {
+    fn position(&self) -> Option<(f32, f32)> {
+        Some(self.position)
+    }
+
+    fn set_position(&mut self, pos: (f32, f32)) {
+        self.position = pos;
+    }
+
+    fn monitor_size(&self) -> (f32, f32) {
+        self.monitor_size
+    }
+
+    fn cursor_position(&self) -> Option<(f32, f32)> {
+        self.cursor
+    }
+
+    fn is_visible(&self) -> bool {
+        true
+    }
+}